@@ -0,0 +1,26 @@
+#![no_main]
+
+use common::{actor::ActorId, id::Id};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|s: &str| {
+    if let Ok(id) = Id::new(s) {
+        let displayed = id.to_string();
+        assert_eq!(displayed, s.trim().to_ascii_lowercase());
+        assert_eq!(Id::new(&displayed), Ok(id));
+        assert_eq!(Id::try_from_slice(id.as_slice()), Ok(id));
+    }
+
+    if let Ok(id) = ActorId::new(s) {
+        let displayed = id.to_string();
+        assert_eq!(displayed, s.trim());
+        assert_eq!(
+            ActorId::new(&displayed).map(|id| id.to_string()),
+            Ok(displayed.clone())
+        );
+        assert_eq!(
+            ActorId::try_from_slice(id.as_slice()).map(|id| id.to_string()),
+            Ok(displayed)
+        );
+    }
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use common::{actor::ActorId, id::Id};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(id) = Id::try_from_slice(bytes) {
+        assert_eq!(Id::try_from_slice(id.as_slice()), Ok(id));
+
+        let displayed = id.to_string();
+        if id != Id::default() {
+            assert_eq!(Id::new(&displayed), Ok(id));
+        }
+    }
+
+    if let Ok(id) = ActorId::try_from_slice(bytes) {
+        let displayed = id.to_string();
+        assert_eq!(
+            ActorId::try_from_slice(id.as_slice()).map(|id| id.to_string()),
+            Ok(displayed.clone())
+        );
+        if !displayed.is_empty() {
+            assert_eq!(
+                ActorId::new(&displayed).map(|id| id.to_string()),
+                Ok(displayed)
+            );
+        }
+    }
+});
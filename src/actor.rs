@@ -1,3 +1,4 @@
+use crate::util::name::{normalize_display_name, normalize_name, NameError};
 use candid::{CandidType, Principal};
 use derive_more::{AsRef, Display, From, Into};
 use ic_stable_structures::{storable::Bound, Storable};
@@ -5,8 +6,12 @@ use serde::{Deserialize, Serialize};
 
 pub mod account;
 pub mod id;
+pub mod role;
 
-pub use id::{ActorId, ActorIdError};
+pub use id::{
+    ActorId, ActorIdError, ActorIdHistory, ActorIdRenameError, ActorIdResolution, PreviousActorId,
+};
+pub use role::{has_permission, Action, Actor, ActorKind, ActorRole, Resource};
 
 #[derive(
     CandidType,
@@ -42,7 +47,9 @@ impl Storable for ActorPrincipal {
     };
 }
 
-/// New type for actor name up to 50 characters
+/// New type for actor name up to 50 characters.
+///
+/// Names are validated by [`normalize_display_name`].
 #[derive(
     CandidType,
     Clone,
@@ -72,22 +79,28 @@ impl Storable for ActorName {
     }
 
     const BOUND: Bound = Bound::Bounded {
-        max_size: 200,
+        max_size: Self::MAX_LENGTH_IN_BYTES as u32,
         is_fixed_size: false,
     };
 }
 
 impl ActorName {
+    /// The maximum length of a name in grapheme clusters
+    pub const MAX_LENGTH: usize = 50;
+    /// The maximum length of a name in bytes
+    pub const MAX_LENGTH_IN_BYTES: usize = 200;
+
     pub fn new<T: AsRef<str>>(name: T) -> Result<Self, ActorNameError> {
-        let name = name.as_ref();
-        if name.chars().count() > 50 {
-            return Err(ActorNameError::TooLong);
-        }
-        Ok(ActorName(name.to_string()))
+        let name =
+            normalize_display_name(name.as_ref(), Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(ActorName(name))
     }
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ActorNameError {
-    TooLong,
+    /// Create a name for one of the platform's own actors, which may use a reserved name
+    pub fn new_for_platform<T: AsRef<str>>(name: T) -> Result<Self, ActorNameError> {
+        let name = normalize_name(name.as_ref(), Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(ActorName(name))
+    }
 }
+
+pub type ActorNameError = NameError;
@@ -1,10 +1,42 @@
-use crate::consts::IPFS_GATEWAY;
+use super::{ActorKind, ActorName, ActorNameError, ActorPrincipal};
+use crate::{
+    market::{MarketId, MarketName},
+    store::{StoreId, StoreName},
+    util::{
+        locale::LocalizedText,
+        mail::{validate_mail_address, MailAddressError},
+        name::normalize_name,
+    },
+};
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
+pub mod image;
+pub mod verification;
+
+pub use image::{
+    AccountImage, AccountImageBlob, AccountImageError, AccountImageUrlConfig, BlobImageUrl,
+};
+pub use verification::{
+    LockedMailVerification, MailVerification, MailVerificationError, PendingMailVerification,
+    VerifiedMailAddress,
+};
+
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum AccountPageResponse {
     User(UserAccountPageResponse),
+    Store(StoreAccountPageResponse),
+    Market(MarketAccountPageResponse),
+}
+
+impl AccountPageResponse {
+    pub fn kind(&self) -> ActorKind {
+        match self {
+            AccountPageResponse::User(_) => ActorKind::User,
+            AccountPageResponse::Store(_) => ActorKind::Store,
+            AccountPageResponse::Market(_) => ActorKind::Market,
+        }
+    }
 }
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -22,6 +54,82 @@ impl UserAccountPageResponse {
         self.mail_address.clone_from(&request.mail_address);
         self.image.clone_from(&request.image);
     }
+
+    /// Apply the set fields of the patch, returning the fields whose values changed
+    pub fn apply(&mut self, patch: &UserProfilePatch) -> Vec<ProfileField> {
+        fn set<T: PartialEq + Clone>(
+            current: &mut T,
+            value: &Option<T>,
+            field: ProfileField,
+            changed: &mut Vec<ProfileField>,
+        ) {
+            if let Some(value) = value {
+                if current != value {
+                    current.clone_from(value);
+                    changed.push(field);
+                }
+            }
+        }
+
+        let mut changed = Vec::new();
+        set(
+            &mut self.name,
+            &patch.name,
+            ProfileField::Name,
+            &mut changed,
+        );
+        set(
+            &mut self.birth_name,
+            &patch.birth_name,
+            ProfileField::BirthName,
+            &mut changed,
+        );
+        set(
+            &mut self.mail_address,
+            &patch.mail_address,
+            ProfileField::MailAddress,
+            &mut changed,
+        );
+        set(
+            &mut self.image,
+            &patch.image,
+            ProfileField::Image,
+            &mut changed,
+        );
+        changed
+    }
+
+    /// Get the patch that turns this profile into `other`, with only the differing fields set
+    pub fn diff(&self, other: &UserAccountPageResponse) -> UserProfilePatch {
+        fn changed<T: PartialEq + Clone>(before: &T, after: &T) -> Option<T> {
+            (before != after).then(|| after.clone())
+        }
+
+        UserProfilePatch {
+            name: changed(&self.name, &other.name),
+            birth_name: changed(&self.birth_name, &other.birth_name),
+            mail_address: changed(&self.mail_address, &other.mail_address),
+            image: changed(&self.image, &other.image),
+        }
+    }
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StoreAccountPageResponse {
+    pub id: StoreId,
+    pub name: LocalizedText<StoreName>,
+    pub image: AccountImage,
+    /// Actors with the `StoreOperator` role for the store
+    pub operators: Vec<ActorPrincipal>,
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MarketAccountPageResponse {
+    pub id: MarketId,
+    pub name: LocalizedText<MarketName>,
+    pub image: AccountImage,
+    /// Actors with the `MarketCurator` role for the market
+    pub curators: Vec<ActorPrincipal>,
 }
 
 #[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
@@ -39,6 +147,26 @@ pub struct SetUserProfileRequest {
 }
 
 impl SetUserProfileRequest {
+    /// Validate every field, collecting an error for each invalid one, and return the request
+    /// with the names normalized, which is what should be stored.
+    ///
+    /// `name` follows the [`ActorName`] rules. `birth_name` follows them too except that it may
+    /// be a reserved name. `birth_name` and `mail_address` may be empty, which means they are
+    /// not set.
+    pub fn validate(&self) -> Result<Self, Vec<ProfileFieldError>> {
+        let patch = validate_profile_fields(
+            Some(&self.name),
+            Some(&self.birth_name),
+            Some(&self.mail_address),
+            Some(&self.image),
+        )?;
+        Ok(Self {
+            name: patch.name.unwrap_or_default(),
+            birth_name: patch.birth_name.unwrap_or_default(),
+            ..self.clone()
+        })
+    }
+
     pub fn equal_to_response(&self, response: UserAccountPageResponse) -> bool {
         self.name == response.name
             && self.birth_name == response.birth_name
@@ -58,31 +186,280 @@ impl From<UserAccountPageResponse> for SetUserProfileRequest {
     }
 }
 
+/// Partial update of a user profile, where `None` leaves the field unchanged
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct UserProfilePatch {
+    pub name: Option<String>,
+    pub birth_name: Option<String>,
+    pub mail_address: Option<String>,
+    pub image: Option<AccountImage>,
+}
+
+impl UserProfilePatch {
+    pub fn is_empty(&self) -> bool {
+        self.fields().is_empty()
+    }
+
+    /// Get the fields that the patch sets
+    pub fn fields(&self) -> Vec<ProfileField> {
+        [
+            (self.name.is_some(), ProfileField::Name),
+            (self.birth_name.is_some(), ProfileField::BirthName),
+            (self.mail_address.is_some(), ProfileField::MailAddress),
+            (self.image.is_some(), ProfileField::Image),
+        ]
+        .into_iter()
+        .filter_map(|(is_set, field)| is_set.then_some(field))
+        .collect()
+    }
+
+    /// Validate the set fields with the same rules as [`SetUserProfileRequest::validate`] and
+    /// return the patch with the names normalized
+    pub fn validate(&self) -> Result<Self, Vec<ProfileFieldError>> {
+        validate_profile_fields(
+            self.name.as_ref(),
+            self.birth_name.as_ref(),
+            self.mail_address.as_ref(),
+            self.image.as_ref(),
+        )
+    }
+}
+
+impl From<SetUserProfileRequest> for UserProfilePatch {
+    fn from(request: SetUserProfileRequest) -> Self {
+        Self {
+            name: Some(request.name),
+            birth_name: Some(request.birth_name),
+            mail_address: Some(request.mail_address),
+            image: Some(request.image),
+        }
+    }
+}
+
+fn validate_profile_fields(
+    name: Option<&String>,
+    birth_name: Option<&String>,
+    mail_address: Option<&String>,
+    image: Option<&AccountImage>,
+) -> Result<UserProfilePatch, Vec<ProfileFieldError>> {
+    let mut errors = Vec::new();
+
+    let name = name.and_then(|name| match ActorName::new(name) {
+        Ok(name) => Some(name.into()),
+        Err(error) => {
+            errors.push(ProfileFieldError::Name(error));
+            None
+        }
+    });
+    // Birth names are never shown as the account's name, so reserved names are fine
+    let birth_name = birth_name.and_then(|birth_name| {
+        if birth_name.is_empty() {
+            return Some(String::new());
+        }
+        match normalize_name(
+            birth_name,
+            ActorName::MAX_LENGTH,
+            ActorName::MAX_LENGTH_IN_BYTES,
+        ) {
+            Ok(birth_name) => Some(birth_name),
+            Err(error) => {
+                errors.push(ProfileFieldError::BirthName(error));
+                None
+            }
+        }
+    });
+    if let Some(Err(error)) = mail_address
+        .filter(|mail_address| !mail_address.is_empty())
+        .map(|mail_address| validate_mail_address(mail_address))
+    {
+        errors.push(ProfileFieldError::MailAddress(error));
+    }
+    if let Some(Err(error)) = image.map(AccountImage::validate) {
+        errors.push(ProfileFieldError::Image(error));
+    }
+
+    if errors.is_empty() {
+        Ok(UserProfilePatch {
+            name,
+            birth_name,
+            mail_address: mail_address.cloned(),
+            image: image.cloned(),
+        })
+    } else {
+        Err(errors)
+    }
+}
+
+/// Field of a user profile
+#[derive(
+    CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum ProfileField {
+    Name,
+    BirthName,
+    MailAddress,
+    Image,
+}
+
 #[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SetUserProfileError {
     #[error("account (key: {0}) not found (AccontPageError::AccountNotFound)")]
     AccountNotFound(Principal),
     #[error("account (key: {0}) is not a user (SetUserProfileError::AccountIsNotUser)")]
     AccountIsNotUser(Principal),
+    #[error("profile has invalid fields: {0:?} (SetUserProfileError::InvalidProfile)")]
+    InvalidProfile(Vec<ProfileFieldError>),
 }
 
-#[derive(Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
-pub enum AccountImage {
-    None,
-    CID(String),
-    BLOB(Vec<u8>),
+/// Error for one field of [`SetUserProfileRequest`]
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ProfileFieldError {
+    #[error("name: {0}")]
+    Name(ActorNameError),
+    #[error("birth name: {0}")]
+    BirthName(ActorNameError),
+    #[error("mail address: {0}")]
+    MailAddress(MailAddressError),
+    #[error("image: {0}")]
+    Image(AccountImageError),
 }
 
-impl AccountImage {
-    pub fn to_url(&self) -> String {
-        match self {
-            AccountImage::None => "".to_string(),
-            AccountImage::CID(cid) => format!("{}{}", IPFS_GATEWAY, cid),
-            AccountImage::BLOB(_) => "".to_string(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::mime::MimeImage;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    fn request() -> SetUserProfileRequest {
+        SetUserProfileRequest {
+            name: "Anthol Shopper".to_string(),
+            birth_name: String::new(),
+            mail_address: "shopper@example.com".to_string(),
+            image: AccountImage::BLOB(AccountImageBlob::new(PNG.to_vec()).unwrap()),
         }
     }
 
-    pub fn is_none(&self) -> bool {
-        self == &AccountImage::None
+    #[test]
+    fn test_validate_profile() {
+        assert_eq!(request().validate(), Ok(request()));
+        let request_without_optionals = SetUserProfileRequest {
+            mail_address: String::new(),
+            image: AccountImage::None,
+            ..request()
+        };
+        assert_eq!(
+            request_without_optionals.validate(),
+            Ok(request_without_optionals)
+        );
+
+        let invalid = SetUserProfileRequest {
+            name: " ".to_string(),
+            birth_name: "a".repeat(51),
+            mail_address: "shopper@localhost".to_string(),
+            image: AccountImage::BLOB(AccountImageBlob {
+                mime: MimeImage::Gif,
+                bytes: b"GIF00a".to_vec(),
+            }),
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(vec![
+                ProfileFieldError::Name(ActorNameError::Empty),
+                ProfileFieldError::BirthName(ActorNameError::TooLong),
+                ProfileFieldError::MailAddress(MailAddressError::InvalidDomain),
+                ProfileFieldError::Image(AccountImageError::UnsupportedFormat),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_profile_normalizes_names() {
+        let validated = SetUserProfileRequest {
+            name: "  Anthol \t Shopper ".to_string(),
+            birth_name: " Root ".to_string(),
+            ..request()
+        }
+        .validate()
+        .unwrap();
+        assert_eq!(validated.name, "Anthol Shopper");
+        assert_eq!(validated.birth_name, "Root");
+
+        assert_eq!(
+            SetUserProfileRequest {
+                name: "Root".to_string(),
+                ..request()
+            }
+            .validate(),
+            Err(vec![ProfileFieldError::Name(ActorNameError::Reserved)])
+        );
+
+        let patch = UserProfilePatch {
+            name: Some(" Shopper ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(patch.validate().unwrap().name.as_deref(), Some("Shopper"));
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let mut profile = UserAccountPageResponse {
+            name: "Anthol Shopper".to_string(),
+            birth_name: String::new(),
+            mail_address: "shopper@example.com".to_string(),
+            image: AccountImage::None,
+        };
+        let patch = UserProfilePatch {
+            name: Some("Anthol Shopper".to_string()),
+            image: Some(AccountImage::CID(CID.to_string())),
+            ..Default::default()
+        };
+        assert_eq!(patch.fields(), [ProfileField::Name, ProfileField::Image]);
+        assert_eq!(patch.validate(), Ok(patch.clone()));
+
+        let before = profile.clone();
+        assert_eq!(profile.apply(&patch), [ProfileField::Image]);
+        assert_eq!(profile.image, AccountImage::CID(CID.to_string()));
+        assert_eq!(profile.mail_address, before.mail_address);
+        assert!(profile.apply(&patch).is_empty());
+        assert!(profile.apply(&UserProfilePatch::default()).is_empty());
+
+        assert_eq!(
+            UserProfilePatch {
+                mail_address: Some("shopper".to_string()),
+                ..Default::default()
+            }
+            .validate(),
+            Err(vec![ProfileFieldError::MailAddress(
+                MailAddressError::MissingAt
+            )])
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = UserAccountPageResponse {
+            name: "Anthol Shopper".to_string(),
+            birth_name: String::new(),
+            mail_address: "shopper@example.com".to_string(),
+            image: AccountImage::None,
+        };
+        let after = UserAccountPageResponse {
+            name: "Shopper".to_string(),
+            mail_address: "shopper@example.org".to_string(),
+            ..before.clone()
+        };
+
+        let patch = before.diff(&after);
+        assert_eq!(
+            patch.fields(),
+            [ProfileField::Name, ProfileField::MailAddress]
+        );
+        assert!(before.diff(&before).is_empty());
+
+        let mut patched = before.clone();
+        assert_eq!(patched.apply(&patch), patch.fields());
+        assert_eq!(patched, after);
     }
 }
@@ -0,0 +1,329 @@
+use crate::media::{
+    ipfs::{Cid, CidError, IpfsGatewayConfig},
+    mime::{Mime, MimeImage},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use candid::{CandidType, Decode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AccountImage {
+    None,
+    CID(String),
+    BLOB(AccountImageBlob),
+}
+
+impl AccountImage {
+    /// The maximum size of a BLOB image in bytes
+    pub const MAX_BLOB_SIZE: usize = 256 * 1024;
+
+    /// Check that a CID image has a valid CID, optionally followed by a path inside it, and that
+    /// a BLOB image is a PNG, JPEG, GIF or WebP image within [`Self::MAX_BLOB_SIZE`] and of the
+    /// declared type
+    pub fn validate(&self) -> Result<(), AccountImageError> {
+        match self {
+            AccountImage::BLOB(blob) => blob.validate(),
+            AccountImage::CID(cid) => Self::parse_cid(cid)
+                .map(|_| ())
+                .map_err(AccountImageError::InvalidCid),
+            AccountImage::None => Ok(()),
+        }
+    }
+
+    /// Get the URL of the image with the default configuration, where BLOB images are `data:` URLs
+    pub fn to_url(&self) -> String {
+        self.to_url_with(&AccountImageUrlConfig::default())
+    }
+
+    /// Get the URL of the image. CID images use the preferred gateway and keep the path after
+    /// the CID, e.g. `<cid>/avatar.png`, and have no URL if the CID is invalid.
+    pub fn to_url_with(&self, config: &AccountImageUrlConfig) -> String {
+        match self {
+            AccountImage::None => "".to_string(),
+            AccountImage::CID(cid) => Self::parse_cid(cid)
+                .ok()
+                .and_then(|(cid, path)| config.ipfs.url(&cid, path))
+                .unwrap_or_default(),
+            AccountImage::BLOB(blob) => match &config.blob {
+                BlobImageUrl::DataUrl => blob.to_data_url(),
+                BlobImageUrl::AssetCanister { base_url } => {
+                    format!("{}/{}", base_url.trim_end_matches('/'), blob.asset_path())
+                }
+            },
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self == &AccountImage::None
+    }
+
+    /// Split a CID image into its CID and the path after it, which may be empty
+    fn parse_cid(cid: &str) -> Result<(Cid, &str), CidError> {
+        let cid = cid.trim();
+        let (cid, path) = cid.split_once('/').unwrap_or((cid, ""));
+        Ok((Cid::new(cid)?, path))
+    }
+
+    /// Decode Candid bytes of an image, migrating bytes stored before BLOB images carried a
+    /// MIME type
+    pub fn decode_or_migrate(bytes: &[u8]) -> Result<Self, candid::Error> {
+        /// Shape of [`AccountImage`] before BLOB images carried a MIME type
+        #[derive(CandidType, Deserialize)]
+        #[allow(clippy::upper_case_acronyms)]
+        enum LegacyAccountImage {
+            None,
+            CID(String),
+            BLOB(Vec<u8>),
+        }
+
+        Decode!(bytes, Self).or_else(|_| {
+            Ok(match Decode!(bytes, LegacyAccountImage)? {
+                LegacyAccountImage::None => AccountImage::None,
+                LegacyAccountImage::CID(cid) => AccountImage::CID(cid),
+                LegacyAccountImage::BLOB(bytes) => {
+                    AccountImage::BLOB(AccountImageBlob::from_legacy_bytes(bytes))
+                }
+            })
+        })
+    }
+}
+
+/// Image uploaded as bytes
+#[derive(Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountImageBlob {
+    pub mime: MimeImage,
+    pub bytes: Vec<u8>,
+}
+
+impl AccountImageBlob {
+    /// Create a BLOB image, detecting its type from the bytes
+    pub fn new(bytes: Vec<u8>) -> Result<Self, AccountImageError> {
+        let mime = Self::sniff(&bytes).ok_or(AccountImageError::UnsupportedFormat)?;
+        let blob = Self { mime, bytes };
+        blob.validate()?;
+        Ok(blob)
+    }
+
+    /// Migrate plain bytes, which BLOB images were before they carried a MIME type, detecting
+    /// the type without validating it
+    pub fn from_legacy_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            mime: MimeImage::sniff(&bytes)
+                .unwrap_or_else(|| MimeImage::Other("octet-stream".to_string())),
+            bytes,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AccountImageError> {
+        if self.bytes.len() > AccountImage::MAX_BLOB_SIZE {
+            return Err(AccountImageError::TooLarge(self.bytes.len() as u64));
+        }
+        let detected = Self::sniff(&self.bytes).ok_or(AccountImageError::UnsupportedFormat)?;
+        if detected != self.mime {
+            return Err(AccountImageError::MimeMismatch {
+                declared: self.mime.clone(),
+                detected,
+            });
+        }
+        Ok(())
+    }
+
+    /// Detect the supported formats: PNG, JPEG, GIF and WebP
+    fn sniff(bytes: &[u8]) -> Option<MimeImage> {
+        MimeImage::sniff(bytes).filter(|mime| {
+            matches!(
+                mime,
+                MimeImage::Png | MimeImage::Jpeg | MimeImage::Gif | MimeImage::Webp
+            )
+        })
+    }
+
+    /// Get the image as a `data:` URL
+    pub fn to_data_url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            Mime::Image(self.mime.clone()),
+            STANDARD.encode(&self.bytes)
+        )
+    }
+
+    /// Get the path of the image in an asset canister, named after the SHA-256 of its bytes so
+    /// that it can be cached forever
+    pub fn asset_path(&self) -> String {
+        let hash = Sha256::digest(&self.bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        match self.mime.extension() {
+            Some(extension) => format!("{}.{}", hash, extension),
+            None => hash,
+        }
+    }
+}
+
+/// How to render the URLs of account images
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccountImageUrlConfig {
+    pub ipfs: IpfsGatewayConfig,
+    pub blob: BlobImageUrl,
+}
+
+/// How to render the URLs of BLOB images
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BlobImageUrl {
+    /// Embed the image as a `data:` URL
+    #[default]
+    DataUrl,
+    /// Link to the image uploaded to an asset canister at [`AccountImageBlob::asset_path`]
+    AssetCanister { base_url: String },
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AccountImageError {
+    #[error("image of {0} bytes is larger than {max} bytes", max = AccountImage::MAX_BLOB_SIZE)]
+    TooLarge(u64),
+    #[error("image is not a PNG, JPEG, GIF or WebP image")]
+    UnsupportedFormat,
+    #[error("image is declared as image/{declared} but is image/{detected}")]
+    MimeMismatch {
+        declared: MimeImage,
+        detected: MimeImage,
+    },
+    #[error("image has an invalid CID: {0}")]
+    InvalidCid(CidError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    #[test]
+    fn test_validate_image() {
+        let mut large = PNG.to_vec();
+        large.resize(AccountImage::MAX_BLOB_SIZE, 0);
+        assert!(AccountImageBlob::new(large.clone()).is_ok());
+
+        large.push(0);
+        assert_eq!(
+            AccountImageBlob::new(large),
+            Err(AccountImageError::TooLarge(
+                AccountImage::MAX_BLOB_SIZE as u64 + 1
+            ))
+        );
+        assert_eq!(
+            AccountImageBlob::new(b"\xff\xd8\xff\xe0".to_vec())
+                .unwrap()
+                .mime,
+            MimeImage::Jpeg
+        );
+        assert_eq!(
+            AccountImageBlob::new(b"RIFF\0\0\0\0WEBPVP8 ".to_vec())
+                .unwrap()
+                .mime,
+            MimeImage::Webp
+        );
+        assert_eq!(
+            AccountImageBlob::new(b"<svg></svg>".to_vec()),
+            Err(AccountImageError::UnsupportedFormat)
+        );
+
+        let mislabeled = AccountImage::BLOB(AccountImageBlob {
+            mime: MimeImage::Gif,
+            bytes: PNG.to_vec(),
+        });
+        assert_eq!(
+            mislabeled.validate(),
+            Err(AccountImageError::MimeMismatch {
+                declared: MimeImage::Gif,
+                detected: MimeImage::Png
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_url() {
+        assert_eq!(AccountImage::None.to_url(), "");
+        assert_eq!(
+            AccountImage::CID(CID.to_string()).to_url(),
+            format!("https://ipfs.anthol.net/ipfs/{}", CID)
+        );
+        let with_path = AccountImage::CID(format!("{}/images/avatar.png", CID));
+        assert_eq!(with_path.validate(), Ok(()));
+        assert_eq!(
+            with_path.to_url(),
+            format!("https://ipfs.anthol.net/ipfs/{}/images/avatar.png", CID)
+        );
+        assert_eq!(AccountImage::CID("cid".to_string()).to_url(), "");
+        assert_eq!(
+            AccountImage::CID("cid/avatar.png".to_string()).validate(),
+            Err(AccountImageError::InvalidCid(
+                CidError::UnsupportedMultibase("c".to_string())
+            ))
+        );
+        assert_eq!(
+            AccountImage::CID("cid".to_string()).validate(),
+            Err(AccountImageError::InvalidCid(
+                CidError::UnsupportedMultibase("c".to_string())
+            ))
+        );
+
+        let image = AccountImage::BLOB(AccountImageBlob::new(PNG.to_vec()).unwrap());
+        assert_eq!(
+            image.to_url(),
+            "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg=="
+        );
+
+        let config = AccountImageUrlConfig {
+            blob: BlobImageUrl::AssetCanister {
+                base_url: "https://assets.anthol.net/account-images/".to_string(),
+            },
+            ..Default::default()
+        };
+        let url = image.to_url_with(&config);
+        assert!(url.starts_with("https://assets.anthol.net/account-images/"));
+        assert!(url.ends_with(".png"));
+        assert_eq!(
+            url.len(),
+            "https://assets.anthol.net/account-images/".len() + 64 + 4
+        );
+    }
+
+    #[test]
+    fn test_candid_round_trip() {
+        let image = AccountImage::BLOB(AccountImageBlob::new(PNG.to_vec()).unwrap());
+        let bytes = Encode!(&image).unwrap();
+        assert_eq!(Decode!(&bytes, AccountImage).unwrap(), image);
+        assert_eq!(AccountImage::decode_or_migrate(&bytes).unwrap(), image);
+
+        let bin = bincode::serialize(&image).unwrap();
+        assert_eq!(bincode::deserialize::<AccountImage>(&bin).unwrap(), image);
+    }
+
+    #[test]
+    fn test_migrate_plain_bytes() {
+        #[derive(CandidType)]
+        #[allow(clippy::upper_case_acronyms)]
+        enum LegacyAccountImage {
+            CID(String),
+            BLOB(Vec<u8>),
+        }
+
+        let bytes = Encode!(&LegacyAccountImage::BLOB(PNG.to_vec())).unwrap();
+        assert!(Decode!(&bytes, AccountImage).is_err());
+        assert_eq!(
+            AccountImage::decode_or_migrate(&bytes).unwrap(),
+            AccountImage::BLOB(AccountImageBlob::new(PNG.to_vec()).unwrap())
+        );
+
+        let bytes = Encode!(&LegacyAccountImage::CID(CID.to_string())).unwrap();
+        assert_eq!(
+            AccountImage::decode_or_migrate(&bytes).unwrap(),
+            AccountImage::CID(CID.to_string())
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use crate::util::{
+    mail::{validate_mail_address, MailAddressError},
+    rng::{EntropyPool, RngError},
+};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const NANOS_PER_MINUTE: u64 = 60 * 1_000_000_000;
+const SALT_LENGTH: usize = 16;
+
+/// Verification state of the mail address of a user.
+///
+/// A verification sends a one-time code to the new address, of which only a salted hash is
+/// kept. While it is pending, the previously verified address stays verified.
+/// Wrong codes are counted across restarts, and too many of them lock new verifications for
+/// [`MailVerification::LOCKOUT`], so that codes cannot be guessed by restarting.
+///
+/// All timestamps are nanoseconds since the Unix epoch, as returned by `ic_cdk::api::time`.
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum MailVerification {
+    #[default]
+    Unverified,
+    Pending(PendingMailVerification),
+    Verified(VerifiedMailAddress),
+    Locked(LockedMailVerification),
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifiedMailAddress {
+    pub address: String,
+    pub verified_at: u64,
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingMailVerification {
+    pub address: String,
+    code_hash: Vec<u8>,
+    salt: Vec<u8>,
+    pub sent_at: u64,
+    pub expires_at: u64,
+    /// Number of wrong codes entered so far
+    pub attempts: u32,
+    /// Address verified before this verification started
+    pub verified: Option<VerifiedMailAddress>,
+}
+
+/// Verification locked after too many wrong codes
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedMailVerification {
+    pub address: String,
+    /// No verification can be started before this time
+    pub locked_until: u64,
+    /// Address verified before the locked verification started
+    pub verified: Option<VerifiedMailAddress>,
+}
+
+impl PendingMailVerification {
+    fn hash_code(address: &str, salt: &[u8], code: &str) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(salt);
+        hasher.update(address.as_bytes());
+        hasher.update([0]);
+        hasher.update(code.as_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    fn matches(&self, code: &str) -> bool {
+        let hash = Self::hash_code(&self.address, &self.salt, code.trim());
+        // Compare without short-circuiting, so that timing does not leak the hash
+        hash.len() == self.code_hash.len()
+            && hash
+                .iter()
+                .zip(&self.code_hash)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl MailVerification {
+    /// Number of digits of a code
+    pub const CODE_LENGTH: usize = 6;
+    /// Time until a code expires
+    pub const CODE_LIFETIME: u64 = 15 * NANOS_PER_MINUTE;
+    /// Minimum time between two codes for the same address
+    pub const RESEND_INTERVAL: u64 = NANOS_PER_MINUTE;
+    /// Number of wrong codes after which the verification is locked
+    pub const MAX_ATTEMPTS: u32 = 5;
+    /// Time for which no verification can be started after too many wrong codes
+    pub const LOCKOUT: u64 = 60 * NANOS_PER_MINUTE;
+
+    /// Start verifying the address, returning the code to send to it.
+    ///
+    /// Starting again for a pending address replaces its code once [`Self::RESEND_INTERVAL`]
+    /// has passed. The wrong codes of a pending verification still count for the new one.
+    pub fn start(
+        &mut self,
+        address: &str,
+        pool: &mut EntropyPool,
+        now: u64,
+    ) -> Result<String, MailVerificationError> {
+        validate_mail_address(address).map_err(MailVerificationError::InvalidMailAddress)?;
+
+        match self {
+            MailVerification::Verified(verified) if verified.address == address => {
+                return Err(MailVerificationError::AlreadyVerified);
+            }
+            MailVerification::Pending(pending) if pending.address == address => {
+                let resend_at = pending.sent_at.saturating_add(Self::RESEND_INTERVAL);
+                if now < resend_at {
+                    return Err(MailVerificationError::ResendTooSoon(resend_at));
+                }
+            }
+            MailVerification::Locked(locked) if now < locked.locked_until => {
+                return Err(MailVerificationError::Locked(locked.locked_until));
+            }
+            _ => {}
+        }
+        let attempts = match self {
+            MailVerification::Pending(pending) => pending.attempts,
+            _ => 0,
+        };
+
+        let code = (pool.next_u64()? % 10u64.pow(Self::CODE_LENGTH as u32)).to_string();
+        let code = format!("{:0>width$}", code, width = Self::CODE_LENGTH);
+        let mut salt = vec![0; SALT_LENGTH];
+        pool.fill_bytes(&mut salt)?;
+
+        *self = MailVerification::Pending(PendingMailVerification {
+            address: address.to_string(),
+            code_hash: PendingMailVerification::hash_code(address, &salt, &code),
+            salt,
+            sent_at: now,
+            expires_at: now.saturating_add(Self::CODE_LIFETIME),
+            attempts,
+            verified: self.verified().cloned(),
+        });
+        Ok(code)
+    }
+
+    /// Verify the pending address with the code sent to it.
+    ///
+    /// An expired verification is cancelled and one with too many wrong codes is locked.
+    pub fn verify(
+        &mut self,
+        code: &str,
+        now: u64,
+    ) -> Result<&VerifiedMailAddress, MailVerificationError> {
+        let MailVerification::Pending(pending) = self else {
+            return Err(MailVerificationError::NotPending);
+        };
+
+        if now >= pending.expires_at {
+            self.cancel();
+            return Err(MailVerificationError::Expired);
+        }
+        if !pending.matches(code) {
+            pending.attempts += 1;
+            let remaining_attempts = Self::MAX_ATTEMPTS.saturating_sub(pending.attempts);
+            if remaining_attempts == 0 {
+                *self = MailVerification::Locked(LockedMailVerification {
+                    address: std::mem::take(&mut pending.address),
+                    locked_until: now.saturating_add(Self::LOCKOUT),
+                    verified: pending.verified.take(),
+                });
+                return Err(MailVerificationError::TooManyAttempts);
+            }
+            return Err(MailVerificationError::InvalidCode { remaining_attempts });
+        }
+
+        *self = MailVerification::Verified(VerifiedMailAddress {
+            address: std::mem::take(&mut pending.address),
+            verified_at: now,
+        });
+        match self {
+            MailVerification::Verified(verified) => Ok(verified),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Cancel a pending verification, going back to the previously verified address if any.
+    ///
+    /// A verification with wrong codes is locked until its code would have expired instead,
+    /// so that cancelling does not reset the count of wrong codes any sooner than waiting.
+    pub fn cancel(&mut self) {
+        if let MailVerification::Pending(pending) = self {
+            *self = match pending.verified.take() {
+                verified if pending.attempts > 0 => {
+                    MailVerification::Locked(LockedMailVerification {
+                        address: std::mem::take(&mut pending.address),
+                        locked_until: pending.expires_at,
+                        verified,
+                    })
+                }
+                Some(verified) => MailVerification::Verified(verified),
+                None => MailVerification::Unverified,
+            };
+        }
+    }
+
+    /// Get the verified address, which stays verified while a new one is pending
+    pub fn verified(&self) -> Option<&VerifiedMailAddress> {
+        match self {
+            MailVerification::Unverified => None,
+            MailVerification::Pending(pending) => pending.verified.as_ref(),
+            MailVerification::Verified(verified) => Some(verified),
+            MailVerification::Locked(locked) => locked.verified.as_ref(),
+        }
+    }
+
+    /// Get the verified address, e.g. before checkout
+    pub fn require_verified(&self) -> Result<&str, MailVerificationError> {
+        self.verified()
+            .map(|verified| verified.address.as_str())
+            .ok_or(MailVerificationError::NotVerified)
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MailVerificationError {
+    #[error("mail address is invalid: {0} (MailVerificationError::InvalidMailAddress)")]
+    InvalidMailAddress(MailAddressError),
+    #[error("mail address is already verified (MailVerificationError::AlreadyVerified)")]
+    AlreadyVerified,
+    #[error("code cannot be resent until {0} (MailVerificationError::ResendTooSoon)")]
+    ResendTooSoon(u64),
+    #[error("no verification is pending (MailVerificationError::NotPending)")]
+    NotPending,
+    #[error("code has expired (MailVerificationError::Expired)")]
+    Expired,
+    #[error("too many wrong codes (MailVerificationError::TooManyAttempts)")]
+    TooManyAttempts,
+    #[error("verification is locked until {0} (MailVerificationError::Locked)")]
+    Locked(u64),
+    #[error(
+        "code is wrong, {remaining_attempts} attempts left (MailVerificationError::InvalidCode)"
+    )]
+    InvalidCode { remaining_attempts: u32 },
+    #[error("mail address is not verified (MailVerificationError::NotVerified)")]
+    NotVerified,
+    #[error("entropy pool is not seeded (MailVerificationError::NotSeeded)")]
+    NotSeeded,
+}
+
+impl From<RngError> for MailVerificationError {
+    fn from(error: RngError) -> Self {
+        match error {
+            RngError::NotSeeded | RngError::SeedRequest { .. } => MailVerificationError::NotSeeded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = NANOS_PER_MINUTE;
+
+    fn pool() -> EntropyPool {
+        EntropyPool::with_seed([1; 32])
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut pool = pool();
+        let mut verification = MailVerification::default();
+        assert_eq!(
+            verification.require_verified(),
+            Err(MailVerificationError::NotVerified)
+        );
+        assert_eq!(
+            verification.verify("000000", 0),
+            Err(MailVerificationError::NotPending)
+        );
+
+        let code = verification
+            .start("shopper@example.com", &mut pool, 0)
+            .unwrap();
+        assert_eq!(code.len(), MailVerification::CODE_LENGTH);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(
+            verification.start("shopper@example.com", &mut pool, 0),
+            Err(MailVerificationError::ResendTooSoon(MINUTE))
+        );
+
+        let verified = verification.verify(&code, 5 * MINUTE).unwrap();
+        assert_eq!(verified.address, "shopper@example.com");
+        assert_eq!(verified.verified_at, 5 * MINUTE);
+        assert_eq!(verification.require_verified(), Ok("shopper@example.com"));
+        assert_eq!(
+            verification.start("shopper@example.com", &mut pool, 6 * MINUTE),
+            Err(MailVerificationError::AlreadyVerified)
+        );
+    }
+
+    #[test]
+    fn test_change_address() {
+        let mut pool = pool();
+        let mut verification = MailVerification::default();
+        let code = verification
+            .start("shopper@example.com", &mut pool, 0)
+            .unwrap();
+        verification.verify(&code, MINUTE).unwrap();
+
+        let code = verification
+            .start("shopper@example.org", &mut pool, 2 * MINUTE)
+            .unwrap();
+        // The old address stays verified until the new one is
+        assert_eq!(verification.require_verified(), Ok("shopper@example.com"));
+        verification.verify(&code, 3 * MINUTE).unwrap();
+        assert_eq!(verification.require_verified(), Ok("shopper@example.org"));
+
+        verification
+            .start("shopper@example.net", &mut pool, 4 * MINUTE)
+            .unwrap();
+        verification.cancel();
+        assert_eq!(verification.require_verified(), Ok("shopper@example.org"));
+    }
+
+    #[test]
+    fn test_verify_failures() {
+        let mut pool = pool();
+        let mut verification = MailVerification::default();
+        assert_eq!(
+            verification.start("shopper", &mut pool, 0),
+            Err(MailVerificationError::InvalidMailAddress(
+                MailAddressError::MissingAt
+            ))
+        );
+        assert_eq!(
+            verification.start("shopper@example.com", &mut EntropyPool::new(), 0),
+            Err(MailVerificationError::NotSeeded)
+        );
+
+        let code = verification
+            .start("shopper@example.com", &mut pool, 0)
+            .unwrap();
+        assert_eq!(
+            verification.verify(&code, MailVerification::CODE_LIFETIME),
+            Err(MailVerificationError::Expired)
+        );
+        assert_eq!(verification, MailVerification::Unverified);
+
+        let code = verification
+            .start("shopper@example.com", &mut pool, 0)
+            .unwrap();
+        let wrong = if code == "000000" { "000001" } else { "000000" };
+        for remaining_attempts in (1..MailVerification::MAX_ATTEMPTS).rev() {
+            assert_eq!(
+                verification.verify(wrong, MINUTE),
+                Err(MailVerificationError::InvalidCode { remaining_attempts })
+            );
+        }
+        assert_eq!(
+            verification.verify(&code, MINUTE),
+            Ok(&VerifiedMailAddress {
+                address: "shopper@example.com".to_string(),
+                verified_at: MINUTE
+            })
+        );
+
+        let code = verification
+            .start("shopper@example.org", &mut pool, 2 * MINUTE)
+            .unwrap();
+        let wrong = if code == "000000" { "000001" } else { "000000" };
+        for _ in 1..MailVerification::MAX_ATTEMPTS {
+            verification.verify(wrong, 3 * MINUTE).unwrap_err();
+        }
+        assert_eq!(
+            verification.verify(wrong, 3 * MINUTE),
+            Err(MailVerificationError::TooManyAttempts)
+        );
+        assert_eq!(verification.require_verified(), Ok("shopper@example.com"));
+        assert_eq!(
+            verification.start("shopper@example.org", &mut pool, 4 * MINUTE),
+            Err(MailVerificationError::Locked(
+                3 * MINUTE + MailVerification::LOCKOUT
+            ))
+        );
+    }
+
+    #[test]
+    fn test_restarts_do_not_reset_attempts() {
+        let mut pool = pool();
+        let mut verification = MailVerification::default();
+        let mut now = 0;
+        let mut guesses = 0;
+
+        // Guess once per code and restart as soon as possible, alternating addresses
+        for address in ["shopper@example.com", "shopper@example.org"].repeat(10) {
+            match verification.start(address, &mut pool, now) {
+                Ok(code) => {
+                    let wrong = if code == "000000" { "000001" } else { "000000" };
+                    guesses += 1;
+                    if verification.verify(wrong, now)
+                        == Err(MailVerificationError::TooManyAttempts)
+                    {
+                        break;
+                    }
+                }
+                Err(error) => panic!("unexpected {error:?}"),
+            }
+            now += MailVerification::RESEND_INTERVAL;
+        }
+        assert_eq!(guesses, MailVerification::MAX_ATTEMPTS);
+
+        let locked_until = now + MailVerification::LOCKOUT;
+        for address in ["shopper@example.com", "shopper@example.org"] {
+            assert_eq!(
+                verification.start(address, &mut pool, locked_until - 1),
+                Err(MailVerificationError::Locked(locked_until))
+            );
+        }
+        assert!(verification
+            .start("shopper@example.com", &mut pool, locked_until)
+            .is_ok());
+        assert!(matches!(
+            &verification,
+            MailVerification::Pending(pending) if pending.attempts == 0
+        ));
+    }
+
+    #[test]
+    fn test_cancel_after_wrong_codes() {
+        let mut pool = pool();
+        let mut verification = MailVerification::default();
+        let code = verification
+            .start("shopper@example.com", &mut pool, 0)
+            .unwrap();
+        let wrong = if code == "000000" { "000001" } else { "000000" };
+        verification.verify(wrong, MINUTE).unwrap_err();
+
+        verification.cancel();
+        assert_eq!(
+            verification.start("shopper@example.com", &mut pool, 2 * MINUTE),
+            Err(MailVerificationError::Locked(
+                MailVerification::CODE_LIFETIME
+            ))
+        );
+        assert!(verification
+            .start(
+                "shopper@example.com",
+                &mut pool,
+                MailVerification::CODE_LIFETIME
+            )
+            .is_ok());
+    }
+}
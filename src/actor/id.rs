@@ -6,6 +6,10 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+pub mod history;
+
+pub use history::{ActorIdHistory, ActorIdRenameError, ActorIdResolution, PreviousActorId};
+
 const CAPITAL_MAP_SIZE: usize = 3;
 const MAX_LENGTH_IN_BYTES: usize = 18;
 const BYTE_LENGTH: usize = CAPITAL_MAP_SIZE + MAX_LENGTH_IN_BYTES;
@@ -92,54 +96,108 @@ impl ActorId {
         Ok(Self(bytes))
     }
 
-    fn from_slice_core(slice: &[u8]) -> Option<Self> {
-        match slice.len() {
-            len @ Self::MIN_LENGTH_IN_BYTES_WITH_CAPITAL_MAP
-                ..=Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP => {
-                let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP];
-                unsafe {
-                    std::ptr::copy_nonoverlapping(slice.as_ptr(), bytes.as_mut_ptr(), len);
-                }
-                Some(Self(bytes))
+    fn from_slice_core(slice: &[u8]) -> Result<Self, ActorIdError> {
+        let len = slice.len();
+        if len < Self::MIN_LENGTH_IN_BYTES_WITH_CAPITAL_MAP {
+            return Err(ActorIdError::BytesTooShort);
+        } else if len > Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP {
+            return Err(ActorIdError::BytesTooLong);
+        }
+
+        let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP];
+        unsafe {
+            std::ptr::copy_nonoverlapping(slice.as_ptr(), bytes.as_mut_ptr(), len);
+        }
+
+        let id = Self(bytes);
+        if id.is_canonical() {
+            Ok(id)
+        } else {
+            Err(ActorIdError::InvalidBytes)
+        }
+    }
+
+    /// Check that the bytes, including the capital map, are exactly what `from_str_core` produces
+    /// for the decoded string.
+    ///
+    /// The empty (default) ActorId is accepted as well.
+    fn is_canonical(&self) -> bool {
+        let s = self.to_string();
+        if s.is_empty() {
+            return self.0 == Self::default().0;
+        }
+        matches!(Self::from_str_core(&s), Ok(id) if id.0 == self.0)
+    }
+
+    /// Get the 6-bit value of the character at the given position
+    fn value_at(&self, position: usize) -> u8 {
+        let bit_position = position * Self::BITS_PER_CHAR;
+        let byte_index = bit_position / 8 + Self::CAPITAL_MAP_SIZE;
+        let bit_offset = bit_position % 8;
+
+        unsafe {
+            if bit_offset <= 2 {
+                (*self.0.get_unchecked(byte_index) >> bit_offset) & Self::CHAR_MASK
+            } else {
+                ((*self.0.get_unchecked(byte_index) >> bit_offset)
+                    | (*self.0.get_unchecked(byte_index + 1) << (8 - bit_offset)))
+                    & Self::CHAR_MASK
             }
-            _ => None,
         }
     }
 
+    /// Check whether the character at the given position is marked as capital
+    fn is_capital_at(&self, position: usize) -> bool {
+        let map_byte_index = position / 8;
+        let map_bit_offset = position % 8;
+        unsafe { (*self.0.get_unchecked(map_byte_index) >> map_bit_offset) & 1 == 1 }
+    }
+
     /// Create a new ActorId from a byte slice
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the slice is out of range or the bytes are not a valid ActorId.
     pub fn from_slice(slice: &[u8]) -> Self {
         match Self::from_slice_core(slice) {
-            Some(id) => id,
-            None => panic!("slice length out of range"),
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
         }
     }
 
     /// Try to create a new ActorId from a byte slice
     pub fn try_from_slice(slice: &[u8]) -> Result<Self, ActorIdError> {
+        Self::from_slice_core(slice)
+    }
+
+    /// Create an ActorId from bytes in stable memory.
+    ///
+    /// Old records may hold non-canonical bytes cut at the first inner zero byte, see
+    /// [`crate::id::Id`]. They are loaded unchecked, as they were then.
+    fn from_stored_slice(slice: &[u8]) -> Self {
         match Self::from_slice_core(slice) {
-            Some(id) => Ok(id),
-            None => {
-                let len = slice.len();
-                if len < Self::MIN_LENGTH_IN_BYTES_WITH_CAPITAL_MAP {
-                    Err(ActorIdError::BytesTooShort)
-                } else {
-                    Err(ActorIdError::BytesTooLong)
-                }
+            Ok(id) => id,
+            Err(ActorIdError::InvalidBytes) => {
+                let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP];
+                bytes[..slice.len()].copy_from_slice(slice);
+                Self(bytes)
             }
+            Err(e) => panic!("{}", e),
         }
     }
 
     /// Get the byte representation of the ActorId
+    ///
+    /// Trailing zero bytes are omitted, but the capital map and at least `MIN_LENGTH_IN_BYTES`
+    /// bytes of the id are returned.
     pub fn as_slice(&self) -> &[u8] {
-        for i in
-            Self::MIN_LENGTH_IN_BYTES_WITH_CAPITAL_MAP..Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP
+        let mut len = Self::MAX_LENGTH_IN_BYTES_WITH_CAPITAL_MAP;
+        while len > Self::MIN_LENGTH_IN_BYTES_WITH_CAPITAL_MAP
+            && unsafe { *self.0.get_unchecked(len - 1) } == 0
         {
-            if unsafe { *self.0.get_unchecked(i) } == 0 {
-                return unsafe { self.0.get_unchecked(..i) };
-            }
+            len -= 1;
         }
-
-        &self.0
+        unsafe { self.0.get_unchecked(..len) }
     }
 }
 
@@ -172,47 +230,21 @@ impl Ord for ActorId {
 impl fmt::Display for ActorId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::with_capacity(Self::MAX_LENGTH);
-        let mut bit_position = 0;
-        let mut position = 0;
-
-        while position < Self::MAX_LENGTH {
-            let byte_index = bit_position / 8 + Self::CAPITAL_MAP_SIZE;
-            let bit_offset = bit_position % 8;
-
-            let value = unsafe {
-                if bit_offset <= 2 {
-                    (*self.0.get_unchecked(byte_index) >> bit_offset) & Self::CHAR_MASK
-                } else {
-                    ((*self.0.get_unchecked(byte_index) >> bit_offset)
-                        | (*self.0.get_unchecked(byte_index + 1) << (8 - bit_offset)))
-                        & Self::CHAR_MASK
-                }
-            };
 
+        for position in 0..Self::MAX_LENGTH {
+            let value = self.value_at(position);
             let char = match value {
-                0 => break,
-                1..=26 => {
-                    let map_byte_index = position / 8;
-                    let map_bit_offset = position % 8;
-                    let is_capital = unsafe {
-                        (*self.0.get_unchecked(map_byte_index) >> map_bit_offset) & 1 == 1
-                    };
-
-                    if is_capital {
-                        (value + Self::CAPITAL_LITERAL_OFFSET) as char
-                    } else {
-                        (value + Self::ALPHABET_LITERAL_OFFSET) as char
-                    }
+                1..=26 if self.is_capital_at(position) => {
+                    (value + Self::CAPITAL_LITERAL_OFFSET) as char
                 }
+                1..=26 => (value + Self::ALPHABET_LITERAL_OFFSET) as char,
                 27..=36 => (value + Self::NUMERIC_LITERAL_OFFSET) as char,
                 37 => '-',
                 38 => '_',
-                _ => unreachable!(),
+                _ => break,
             };
 
             result.push(char);
-            bit_position += Self::BITS_PER_CHAR;
-            position += 1;
         }
 
         f.write_str(&result)
@@ -335,7 +367,7 @@ impl Storable for ActorId {
     }
 
     fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Self::from_slice(&bytes)
+        Self::from_stored_slice(&bytes)
     }
 
     const BOUND: Bound = Bound::Bounded {
@@ -361,6 +393,9 @@ pub enum ActorIdError {
 
     #[error("InvalActorId character '{0}' in ActorId.")]
     InvalidCharacter(char),
+
+    #[error("Bytes are not a valid ActorId.")]
+    InvalidBytes,
 }
 
 #[cfg(test)]
@@ -413,6 +448,22 @@ mod tests {
         assert_eq!(id.to_string(), "Anthol_User-123");
     }
 
+    #[test]
+    fn test_from_legacy_stored_bytes() {
+        // The old `as_slice` cut the bytes at the zero byte between "a" and "p", keeping the
+        // capital bit of "X", which is not canonical
+        let id = ActorId::new("abcdeapXyz").unwrap();
+        assert_eq!(id.0[7], 0);
+        let legacy = &id.as_slice()[..7];
+        assert_eq!(
+            ActorId::try_from_slice(legacy),
+            Err(ActorIdError::InvalidBytes)
+        );
+
+        let loaded = ActorId::from_bytes(Cow::Borrowed(legacy));
+        assert_eq!(loaded.to_bytes().as_ref(), legacy);
+    }
+
     #[test]
     fn test_id_try_from_slice() {
         let id = ActorId::new("Anthol_User").unwrap();
@@ -459,4 +510,105 @@ mod tests {
         assert_eq!(id, id_bincode);
         assert_eq!(id.to_string(), id_bincode.to_string());
     }
+
+    #[test]
+    fn test_id_bytes_with_inner_zero_byte() {
+        // The eighth byte holds only zero bits of 'a' and 'p'
+        let id = ActorId::new("abcdeaP").unwrap();
+        assert_eq!(id.as_slice()[7], 0);
+        assert_eq!(ActorId::from_bytes(id.to_bytes()).to_string(), "abcdeaP");
+    }
+
+    #[test]
+    fn test_id_try_from_invalid_slice() {
+        assert_eq!(
+            ActorId::try_from_slice(&[0; 5]),
+            Err(ActorIdError::BytesTooShort)
+        );
+        assert_eq!(
+            ActorId::try_from_slice(&[1; 22]),
+            Err(ActorIdError::BytesTooLong)
+        );
+        // Out-of-range character value
+        assert_eq!(
+            ActorId::try_from_slice(&[0, 0, 0, 0xff, 0xff, 0xff]),
+            Err(ActorIdError::InvalidBytes)
+        );
+        // "abc" with a capital flag on the fourth (missing) character
+        let mut bytes = ActorId::new("abc").unwrap().as_slice().to_vec();
+        bytes[0] |= 0b1000;
+        assert_eq!(
+            ActorId::try_from_slice(&bytes),
+            Err(ActorIdError::InvalidBytes)
+        );
+        assert_eq!(ActorId::try_from_slice(&[0; 6]).unwrap().to_string(), "");
+    }
+
+    mod proptests {
+        use super::*;
+        use crate::id::proptests::{self as util, config};
+        use proptest::prelude::*;
+
+        const ACTOR_ID_PATTERN: &str = "[a-zA-Z0-9_-]{3,24}";
+
+        proptest! {
+            #![proptest_config(config())]
+
+            #[test]
+            fn string_round_trip(s in ACTOR_ID_PATTERN) {
+                let id = ActorId::new(&s).unwrap();
+                prop_assert_eq!(id.to_string(), s);
+            }
+
+            #[test]
+            fn case_insensitive_equality(s in ACTOR_ID_PATTERN) {
+                let id = ActorId::new(&s).unwrap();
+                let lower = ActorId::new(s.to_ascii_lowercase()).unwrap();
+                prop_assert_eq!(id, lower);
+                prop_assert_eq!(id.cmp(&lower), std::cmp::Ordering::Equal);
+            }
+
+            #[test]
+            fn bytes_round_trip(s in ACTOR_ID_PATTERN) {
+                let id = ActorId::new(&s).unwrap();
+                let decoded = ActorId::try_from_slice(id.as_slice()).unwrap();
+                prop_assert_eq!(decoded.to_string(), s);
+            }
+
+            #[test]
+            fn codec_round_trip(s in ACTOR_ID_PATTERN) {
+                let id = ActorId::new(&s).unwrap();
+                prop_assert_eq!(serde_json::to_string(&id).unwrap(), format!("\"{}\"", s));
+                for decoded in util::codec_round_trip(&id) {
+                    prop_assert_eq!(decoded.to_string(), s.as_str());
+                }
+            }
+
+            #[test]
+            fn storable_round_trip(s in ACTOR_ID_PATTERN) {
+                let id = ActorId::new(&s).unwrap();
+                let bytes = id.to_bytes();
+                prop_assert!(bytes.len() <= ActorId::MAX_LENGTH_IN_BYTES + 3);
+                prop_assert_eq!(ActorId::from_bytes(bytes).to_string(), s);
+            }
+
+            #[test]
+            fn arbitrary_str(s in "\\PC{0,32}") {
+                if let Ok(id) = ActorId::new(&s) {
+                    prop_assert_eq!(ActorId::new(id.to_string()).unwrap().to_string(), id.to_string());
+                    prop_assert_eq!(ActorId::try_from_slice(id.as_slice()).unwrap().to_string(), id.to_string());
+                }
+            }
+
+            #[test]
+            fn arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..24)) {
+                if let Ok(id) = ActorId::try_from_slice(&bytes) {
+                    prop_assert_eq!(ActorId::try_from_slice(id.as_slice()).unwrap().0, id.0);
+                    if !id.to_string().is_empty() {
+                        prop_assert_eq!(ActorId::new(id.to_string()).unwrap().0, id.0);
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,250 @@
+use super::ActorId;
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// History of the ActorIds of one actor.
+///
+/// Actors can rename their ActorId once per cool-down window. Previous ids keep resolving to the
+/// current one so that old links keep working, and are held for the actor during the hold period
+/// before other actors can claim them.
+///
+/// All timestamps are nanoseconds since the Unix epoch, as returned by `ic_cdk::api::time`.
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActorIdHistory {
+    current: ActorId,
+    changed_at: u64,
+    previous: Vec<PreviousActorId>,
+}
+
+/// ActorId that an actor used before renaming
+#[derive(CandidType, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviousActorId {
+    pub id: ActorId,
+    /// When the actor renamed away from this id
+    pub used_until: u64,
+}
+
+/// Result of resolving an ActorId against a history
+#[derive(CandidType, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ActorIdResolution {
+    /// The id is the current one
+    Current(ActorId),
+    /// The id is a previous one of the actor, which now uses `current`
+    Alias {
+        current: ActorId,
+        previous: PreviousActorId,
+    },
+}
+
+impl ActorIdResolution {
+    /// Get the current ActorId of the actor
+    pub fn current(&self) -> ActorId {
+        match self {
+            ActorIdResolution::Current(current) => *current,
+            ActorIdResolution::Alias { current, .. } => *current,
+        }
+    }
+}
+
+impl ActorIdHistory {
+    /// Minimum time between two renames
+    pub const COOL_DOWN: u64 = 30 * NANOS_PER_DAY;
+    /// Time after a rename during which the previous id cannot be claimed by other actors
+    pub const HOLD_PERIOD: u64 = 90 * NANOS_PER_DAY;
+
+    /// Create a history for an actor who registered with the id at the given time
+    pub fn new(id: ActorId, created_at: u64) -> Self {
+        Self {
+            current: id,
+            changed_at: created_at,
+            previous: Vec::new(),
+        }
+    }
+
+    /// Get the current ActorId
+    pub fn current(&self) -> ActorId {
+        self.current
+    }
+
+    /// Get the previous ActorIds, oldest first
+    pub fn previous(&self) -> &[PreviousActorId] {
+        &self.previous
+    }
+
+    /// Get the time from which the actor can rename again
+    pub fn next_rename_at(&self) -> u64 {
+        self.changed_at.saturating_add(Self::COOL_DOWN)
+    }
+
+    /// Rename the current ActorId, keeping the old one as an alias.
+    ///
+    /// Changing only the capitalization is always allowed and does not leave an alias.
+    /// Renaming back to one of the previous ids removes it from the aliases.
+    /// Whether the new id is free is up to the caller, e.g. with [`Self::is_claimable`]
+    /// on the history of its previous owner.
+    pub fn rename(&mut self, new_id: ActorId, now: u64) -> Result<(), ActorIdRenameError> {
+        if new_id == self.current {
+            self.current = new_id;
+            return Ok(());
+        }
+
+        let next_rename_at = self.next_rename_at();
+        if now < next_rename_at {
+            return Err(ActorIdRenameError::CoolDown(next_rename_at));
+        }
+
+        self.previous.retain(|previous| previous.id != new_id);
+        self.previous.push(PreviousActorId {
+            id: self.current,
+            used_until: now,
+        });
+        self.current = new_id;
+        self.changed_at = now;
+        Ok(())
+    }
+
+    /// Resolve an ActorId to the current one if it belongs to this actor
+    pub fn resolve(&self, id: &ActorId) -> Option<ActorIdResolution> {
+        if id == &self.current {
+            return Some(ActorIdResolution::Current(self.current));
+        }
+        self.previous
+            .iter()
+            .rev()
+            .find(|previous| &previous.id == id)
+            .map(|previous| ActorIdResolution::Alias {
+                current: self.current,
+                previous: *previous,
+            })
+    }
+
+    /// Check whether another actor can claim the id, i.e. it is neither the current id nor a
+    /// previous id still in its hold period
+    pub fn is_claimable(&self, id: &ActorId, now: u64) -> bool {
+        match self.resolve(id) {
+            None => true,
+            Some(ActorIdResolution::Current(_)) => false,
+            Some(ActorIdResolution::Alias { previous, .. }) => {
+                now >= previous.used_until.saturating_add(Self::HOLD_PERIOD)
+            }
+        }
+    }
+
+    /// Remove a previous id once another actor has claimed it, so that it stops resolving here
+    pub fn release(&mut self, id: &ActorId) -> Option<PreviousActorId> {
+        let index = self
+            .previous
+            .iter()
+            .position(|previous| &previous.id == id)?;
+        Some(self.previous.remove(index))
+    }
+}
+
+impl Storable for ActorIdHistory {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ActorIdRenameError {
+    #[error("ActorId cannot be renamed until {0} (ActorIdRenameError::CoolDown)")]
+    CoolDown(u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = NANOS_PER_DAY;
+
+    fn id(s: &str) -> ActorId {
+        ActorId::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut history = ActorIdHistory::new(id("first"), 0);
+
+        assert_eq!(
+            history.rename(id("second"), 10 * DAY),
+            Err(ActorIdRenameError::CoolDown(30 * DAY))
+        );
+
+        history.rename(id("second"), 30 * DAY).unwrap();
+        assert_eq!(history.current(), id("second"));
+        assert_eq!(
+            history.previous(),
+            &[PreviousActorId {
+                id: id("first"),
+                used_until: 30 * DAY
+            }]
+        );
+        assert_eq!(history.next_rename_at(), 60 * DAY);
+
+        // Capitalization can be changed any time
+        history.rename(id("Second"), 31 * DAY).unwrap();
+        assert_eq!(history.current().to_string(), "Second");
+        assert_eq!(history.previous().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_back() {
+        let mut history = ActorIdHistory::new(id("first"), 0);
+        history.rename(id("second"), 30 * DAY).unwrap();
+        history.rename(id("first"), 60 * DAY).unwrap();
+
+        assert_eq!(history.current(), id("first"));
+        assert_eq!(history.previous().len(), 1);
+        assert_eq!(history.previous()[0].id, id("second"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut history = ActorIdHistory::new(id("first"), 0);
+        history.rename(id("second"), 30 * DAY).unwrap();
+
+        assert_eq!(
+            history.resolve(&id("second")),
+            Some(ActorIdResolution::Current(id("second")))
+        );
+        let resolution = history.resolve(&id("FIRST")).unwrap();
+        assert_eq!(resolution.current(), id("second"));
+        assert!(matches!(resolution, ActorIdResolution::Alias { .. }));
+        assert_eq!(history.resolve(&id("third")), None);
+
+        history.release(&id("first")).unwrap();
+        assert_eq!(history.resolve(&id("first")), None);
+    }
+
+    #[test]
+    fn test_is_claimable() {
+        let mut history = ActorIdHistory::new(id("first"), 0);
+        history.rename(id("second"), 30 * DAY).unwrap();
+
+        assert!(!history.is_claimable(&id("second"), 1000 * DAY));
+        assert!(!history.is_claimable(&id("first"), 119 * DAY));
+        assert!(history.is_claimable(&id("first"), 120 * DAY));
+        assert!(history.is_claimable(&id("third"), 0));
+    }
+
+    #[test]
+    fn test_storable() {
+        let mut history = ActorIdHistory::new(id("First"), 0);
+        history.rename(id("second"), 30 * DAY).unwrap();
+
+        let decoded = ActorIdHistory::from_bytes(history.to_bytes());
+        assert_eq!(decoded, history);
+        assert_eq!(decoded.previous()[0].id.to_string(), "First");
+    }
+}
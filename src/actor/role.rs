@@ -0,0 +1,271 @@
+use super::ActorPrincipal;
+use crate::{item::ItemId, market::MarketId, store::StoreId};
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{storable::Bound, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Kind of the account behind an actor
+#[derive(
+    CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum ActorKind {
+    User,
+    Store,
+    Market,
+}
+
+/// Role granted to an actor.
+///
+/// Store and market roles are scoped to one store or market, so that an operator of one store
+/// cannot edit the items of another.
+#[derive(
+    CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum ActorRole {
+    User,
+    StoreOperator(StoreId),
+    MarketCurator(MarketId),
+    Admin,
+}
+
+/// Action that requires a permission
+#[derive(
+    CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum Action {
+    /// Create, update or delete items of a store
+    EditItem,
+    /// Update the profile of a store
+    EditStore,
+    /// Add or remove items of a market
+    CurateMarket,
+    /// Hide items, stores and markets, or suspend actors
+    Moderate,
+}
+
+/// Resource that an action is performed on
+#[derive(CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub enum Resource {
+    Item { store_id: StoreId, item_id: ItemId },
+    Store(StoreId),
+    Market(MarketId),
+    Actor(ActorPrincipal),
+}
+
+impl Action {
+    /// Whether the action can be performed on the kind of resource at all
+    pub fn applies_to(&self, resource: &Resource) -> bool {
+        matches!(
+            (self, resource),
+            (Action::EditItem, Resource::Item { .. })
+                | (Action::EditStore, Resource::Store(_))
+                | (Action::CurateMarket, Resource::Market(_))
+                | (Action::Moderate, _)
+        )
+    }
+}
+
+impl ActorRole {
+    /// Actions that the role allows on some resources
+    pub fn actions(&self) -> &'static [Action] {
+        match self {
+            ActorRole::User => &[],
+            ActorRole::StoreOperator(_) => &[Action::EditItem, Action::EditStore],
+            ActorRole::MarketCurator(_) => &[Action::CurateMarket],
+            ActorRole::Admin => &[
+                Action::EditItem,
+                Action::EditStore,
+                Action::CurateMarket,
+                Action::Moderate,
+            ],
+        }
+    }
+
+    /// Whether the resource is within the store or market that the role is scoped to
+    pub fn covers(&self, resource: &Resource) -> bool {
+        match (self, resource) {
+            (ActorRole::Admin, _) => true,
+            (ActorRole::StoreOperator(id), Resource::Item { store_id, .. })
+            | (ActorRole::StoreOperator(id), Resource::Store(store_id)) => id == store_id,
+            (ActorRole::MarketCurator(id), Resource::Market(market_id)) => id == market_id,
+            _ => false,
+        }
+    }
+
+    /// Check whether the role allows the action on the resource, i.e. the action is one of
+    /// [`ActorRole::actions`], applies to the resource and the role covers the resource
+    pub fn allows(&self, action: Action, resource: &Resource) -> bool {
+        self.actions().contains(&action) && action.applies_to(resource) && self.covers(resource)
+    }
+}
+
+/// Actor with the roles granted to it
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Actor {
+    pub principal: ActorPrincipal,
+    pub kind: ActorKind,
+    pub roles: Vec<ActorRole>,
+}
+
+impl Actor {
+    pub fn has_role(&self, role: &ActorRole) -> bool {
+        self.roles.contains(role)
+    }
+
+    /// Grant a role, returning whether it was newly granted
+    pub fn grant(&mut self, role: ActorRole) -> bool {
+        if self.has_role(&role) {
+            return false;
+        }
+        self.roles.push(role);
+        true
+    }
+
+    /// Revoke a role, returning whether it was granted
+    pub fn revoke(&mut self, role: &ActorRole) -> bool {
+        let len = self.roles.len();
+        self.roles.retain(|granted| granted != role);
+        self.roles.len() != len
+    }
+}
+
+impl Storable for Actor {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Check whether any role of the actor allows the action on the resource
+pub fn has_permission(actor: &Actor, action: Action, resource: &Resource) -> bool {
+    actor.roles.iter().any(|role| role.allows(action, resource))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+
+    fn actor(roles: Vec<ActorRole>) -> Actor {
+        Actor {
+            principal: Principal::anonymous().into(),
+            kind: ActorKind::User,
+            roles,
+        }
+    }
+
+    #[test]
+    fn test_has_permission() {
+        let store = StoreId::new("store").unwrap();
+        let other_store = StoreId::new("other").unwrap();
+        let market = MarketId::new("market").unwrap();
+        let item = |store_id| Resource::Item {
+            store_id,
+            item_id: ItemId::new("item").unwrap(),
+        };
+
+        let operator = actor(vec![ActorRole::User, ActorRole::StoreOperator(store)]);
+        assert!(has_permission(&operator, Action::EditItem, &item(store)));
+        assert!(has_permission(
+            &operator,
+            Action::EditStore,
+            &Resource::Store(store)
+        ));
+        assert!(!has_permission(
+            &operator,
+            Action::EditItem,
+            &item(other_store)
+        ));
+        assert!(!has_permission(
+            &operator,
+            Action::CurateMarket,
+            &Resource::Market(market)
+        ));
+
+        let curator = actor(vec![ActorRole::MarketCurator(market)]);
+        assert!(has_permission(
+            &curator,
+            Action::CurateMarket,
+            &Resource::Market(market)
+        ));
+        assert!(!has_permission(&curator, Action::EditItem, &item(store)));
+        assert!(!has_permission(
+            &curator,
+            Action::Moderate,
+            &Resource::Market(market)
+        ));
+
+        let admin = actor(vec![ActorRole::Admin]);
+        assert!(has_permission(&admin, Action::Moderate, &item(store)));
+        assert!(has_permission(
+            &admin,
+            Action::Moderate,
+            &Resource::Actor(Principal::anonymous().into())
+        ));
+
+        let user = actor(vec![ActorRole::User]);
+        assert!(!has_permission(&user, Action::EditItem, &item(store)));
+    }
+
+    #[test]
+    fn test_allows_only_listed_actions() {
+        let store = StoreId::new("store").unwrap();
+        let market = MarketId::new("market").unwrap();
+        let resources = [
+            Resource::Item {
+                store_id: store,
+                item_id: ItemId::new("item").unwrap(),
+            },
+            Resource::Store(store),
+            Resource::Market(market),
+            Resource::Actor(Principal::anonymous().into()),
+        ];
+        let roles = [
+            ActorRole::User,
+            ActorRole::StoreOperator(store),
+            ActorRole::MarketCurator(market),
+            ActorRole::Admin,
+        ];
+        let actions = [
+            Action::EditItem,
+            Action::EditStore,
+            Action::CurateMarket,
+            Action::Moderate,
+        ];
+
+        for role in roles {
+            for action in actions {
+                let allowed = resources
+                    .iter()
+                    .any(|resource| role.allows(action, resource));
+                assert_eq!(
+                    allowed,
+                    role.actions().contains(&action),
+                    "{role:?} {action:?}"
+                );
+            }
+        }
+        assert!(!ActorRole::Admin.allows(Action::EditItem, &Resource::Market(market)));
+    }
+
+    #[test]
+    fn test_grant_and_revoke() {
+        let store = StoreId::new("store").unwrap();
+        let mut actor = actor(vec![ActorRole::User]);
+
+        assert!(actor.grant(ActorRole::StoreOperator(store)));
+        assert!(!actor.grant(ActorRole::StoreOperator(store)));
+        assert!(actor.has_role(&ActorRole::StoreOperator(store)));
+        assert_eq!(Actor::from_bytes(actor.to_bytes()), actor);
+
+        assert!(actor.revoke(&ActorRole::StoreOperator(store)));
+        assert!(!actor.revoke(&ActorRole::StoreOperator(store)));
+        assert_eq!(actor.roles, vec![ActorRole::User]);
+    }
+}
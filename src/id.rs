@@ -1,6 +1,10 @@
 use candid::CandidType;
 use ic_stable_structures::{storable::Bound, Storable};
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, fmt, hash::Hash, marker::PhantomData};
+
+pub mod random;
+
+pub use random::{CartId, OrderId, PaymentId, RandomId, RandomIdError, SessionId};
 
 const MAX_LENGTH_IN_BYTES: usize = 16;
 
@@ -23,7 +27,6 @@ impl Id {
 
     const BITS_PER_CHAR: usize = 6;
     const CHAR_MASK: u8 = 0b0011_1111; // 6 bits mask
-    const DATA_BITS_CAPACITY: usize = Self::MAX_LENGTH * Self::BITS_PER_CHAR;
 
     const ALPHABET_LITERAL_OFFSET: u8 = b'a' - 1; // 1-based index
     const NUMERIC_LITERAL_OFFSET: u8 = b'0' - 1 - 26; // 1-based index + 26 for alphabets
@@ -73,84 +76,115 @@ impl Id {
         Ok(Self(bytes))
     }
 
-    fn from_slice_core(slice: &[u8]) -> Option<Self> {
-        match slice.len() {
-            len @ Self::MIN_LENGTH_IN_BYTES..=Self::MAX_LENGTH_IN_BYTES => {
-                let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES];
-                unsafe {
-                    std::ptr::copy_nonoverlapping(slice.as_ptr(), bytes.as_mut_ptr(), len);
-                }
-                Some(Self(bytes))
+    fn from_slice_core(slice: &[u8]) -> Result<Self, IdError> {
+        let len = slice.len();
+        if len < Self::MIN_LENGTH_IN_BYTES {
+            return Err(IdError::BytesTooShort);
+        } else if len > Self::MAX_LENGTH_IN_BYTES {
+            return Err(IdError::BytesTooLong);
+        }
+
+        let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES];
+        unsafe {
+            std::ptr::copy_nonoverlapping(slice.as_ptr(), bytes.as_mut_ptr(), len);
+        }
+
+        let id = Self(bytes);
+        if id.is_canonical() {
+            Ok(id)
+        } else {
+            Err(IdError::InvalidBytes)
+        }
+    }
+
+    /// Check that the bytes are exactly what `from_str_core` produces for the decoded string.
+    ///
+    /// The empty (default) ID is accepted as well.
+    fn is_canonical(&self) -> bool {
+        let s = self.to_string();
+        if s.is_empty() {
+            return self == &Self::default();
+        }
+        matches!(Self::from_str_core(&s), Ok(id) if id == *self)
+    }
+
+    /// Get the 6-bit value of the character at the given position
+    fn value_at(&self, position: usize) -> u8 {
+        let bit_position = position * Self::BITS_PER_CHAR;
+        let byte_index = bit_position / 8;
+        let bit_offset = bit_position % 8;
+
+        unsafe {
+            if bit_offset <= 2 {
+                (*self.0.get_unchecked(byte_index) >> bit_offset) & Self::CHAR_MASK
+            } else {
+                ((*self.0.get_unchecked(byte_index) >> bit_offset)
+                    | (*self.0.get_unchecked(byte_index + 1) << (8 - bit_offset)))
+                    & Self::CHAR_MASK
             }
-            _ => None,
         }
     }
 
     /// Create a new ID from a byte slice
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the slice is out of range or the bytes are not a valid ID.
     pub fn from_slice(slice: &[u8]) -> Self {
         match Self::from_slice_core(slice) {
-            Some(id) => id,
-            None => panic!("slice length out of range"),
+            Ok(id) => id,
+            Err(e) => panic!("{}", e),
         }
     }
 
     /// Try to create a new ID from a byte slice
     pub fn try_from_slice(slice: &[u8]) -> Result<Self, IdError> {
+        Self::from_slice_core(slice)
+    }
+
+    /// Create an ID from bytes in stable memory.
+    ///
+    /// Before canonical bytes were enforced, `as_slice` cut the bytes at the first inner zero
+    /// byte, so old records may hold non-canonical bytes. They are loaded unchecked, as they
+    /// were then, so that they keep their value and write back the same bytes.
+    fn from_stored_slice(slice: &[u8]) -> Self {
         match Self::from_slice_core(slice) {
-            Some(id) => Ok(id),
-            None => {
-                let len = slice.len();
-                if len < Self::MIN_LENGTH_IN_BYTES {
-                    Err(IdError::BytesTooShort)
-                } else {
-                    Err(IdError::BytesTooLong)
-                }
+            Ok(id) => id,
+            Err(IdError::InvalidBytes) => {
+                let mut bytes = [0; Self::MAX_LENGTH_IN_BYTES];
+                bytes[..slice.len()].copy_from_slice(slice);
+                Self(bytes)
             }
+            Err(e) => panic!("{}", e),
         }
     }
 
     /// Get the byte representation of the ID
+    ///
+    /// Trailing zero bytes are omitted, but at least `MIN_LENGTH_IN_BYTES` bytes are returned.
     pub fn as_slice(&self) -> &[u8] {
-        unsafe {
-            for i in Self::MIN_LENGTH_IN_BYTES..Self::MAX_LENGTH_IN_BYTES {
-                if *self.0.get_unchecked(i) == 0 {
-                    return &self.0[..i];
-                }
-            }
+        let mut len = Self::MAX_LENGTH_IN_BYTES;
+        while len > Self::MIN_LENGTH_IN_BYTES && unsafe { *self.0.get_unchecked(len - 1) } == 0 {
+            len -= 1;
         }
-        &self.0
+        unsafe { self.0.get_unchecked(..len) }
     }
 }
 
 impl fmt::Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::with_capacity(Self::MAX_LENGTH);
-        let mut bit_position = 0;
 
-        unsafe {
-            while bit_position < Self::DATA_BITS_CAPACITY {
-                let byte_index = bit_position / 8;
-                let bit_offset = bit_position % 8;
-
-                let value = if bit_offset <= 2 {
-                    (*self.0.get_unchecked(byte_index) >> bit_offset) & Self::CHAR_MASK
-                } else {
-                    ((*self.0.get_unchecked(byte_index) >> bit_offset)
-                        | (*self.0.get_unchecked(byte_index + 1) << (8 - bit_offset)))
-                        & Self::CHAR_MASK
-                };
-
-                let char = match value {
-                    0 => break,
-                    1..=26 => (value + Self::ALPHABET_LITERAL_OFFSET) as char,
-                    27..=36 => (value + Self::NUMERIC_LITERAL_OFFSET) as char,
-                    37 => '-',
-                    _ => unreachable!(),
-                };
-
-                result.push(char);
-                bit_position += Self::BITS_PER_CHAR;
-            }
+        for position in 0..Self::MAX_LENGTH {
+            let value = self.value_at(position);
+            let char = match value {
+                1..=26 => (value + Self::ALPHABET_LITERAL_OFFSET) as char,
+                27..=36 => (value + Self::NUMERIC_LITERAL_OFFSET) as char,
+                37 => '-',
+                _ => break,
+            };
+
+            result.push(char);
         }
 
         f.write_str(&result)
@@ -273,7 +307,7 @@ impl Storable for Id {
     }
 
     fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Self::from_slice(&bytes)
+        Self::from_stored_slice(&bytes)
     }
 
     const BOUND: Bound = Bound::Bounded {
@@ -302,11 +336,204 @@ pub enum IdError {
 
     #[error("ID cannot start or end with a hyphen.")]
     InvalidHyphenPosition,
+
+    #[error("Bytes are not a valid ID.")]
+    InvalidBytes,
+}
+
+/// Kind of entity identified by a [`TypedId`].
+///
+/// Kinds are usually declared together with their ID type by [`typed_id!`](crate::typed_id).
+pub trait IdKind:
+    fmt::Debug + Clone + Copy + PartialEq + Eq + Hash + PartialOrd + Ord + Default + 'static
+{
+    /// Prefix shown by the alternate format (`{:#}`), e.g. `mkt` for `mkt:anthol`
+    const PREFIX: &'static str;
+}
+
+/// [`Id`] tagged with the kind of entity it identifies.
+///
+/// The encoding is the same as [`Id`] in every format, so IDs of different kinds only differ in Rust.
+/// `{}` displays the bare ID and `{:#}` displays it with the kind prefix, e.g. `mkt:anthol`.
+/// Parsing accepts both forms.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct TypedId<K: IdKind>(Id, #[serde(skip)] PhantomData<K>);
+
+impl<K: IdKind> TypedId<K> {
+    /// Create a new typed ID from a string with or without the kind prefix
+    pub fn new<S: AsRef<str>>(id_str: S) -> Result<Self, IdError> {
+        Self::from_str_core(id_str.as_ref())
+    }
+
+    fn from_str_core(s: &str) -> Result<Self, IdError> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix(K::PREFIX)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .unwrap_or(s);
+        Ok(Self::from(Id::new(s)?))
+    }
+
+    /// Get the untyped ID
+    pub fn id(&self) -> Id {
+        self.0
+    }
+}
+
+impl<K: IdKind> fmt::Display for TypedId<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}:{}", K::PREFIX, self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+impl<K: IdKind> From<Id> for TypedId<K> {
+    fn from(id: Id) -> Self {
+        Self(id, PhantomData)
+    }
+}
+
+impl<K: IdKind> From<TypedId<K>> for Id {
+    fn from(id: TypedId<K>) -> Self {
+        id.0
+    }
+}
+
+impl<K: IdKind> AsRef<Id> for TypedId<K> {
+    fn as_ref(&self) -> &Id {
+        &self.0
+    }
+}
+
+impl<K: IdKind> std::str::FromStr for TypedId<K> {
+    type Err = IdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
+    }
+}
+
+impl<K: IdKind> TryFrom<&str> for TypedId<K> {
+    type Error = IdError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
+    }
+}
+
+impl<K: IdKind> TryFrom<String> for TypedId<K> {
+    type Error = IdError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str_core(&value)
+    }
+}
+
+impl<K: IdKind> CandidType for TypedId<K> {
+    fn _ty() -> candid::types::Type {
+        Id::ty()
+    }
+
+    fn idl_serialize<S: candid::types::Serializer>(&self, serializer: S) -> Result<(), S::Error> {
+        self.0.idl_serialize(serializer)
+    }
+}
+
+impl<K: IdKind> Storable for TypedId<K> {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        self.0.to_bytes()
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Self::from(Id::from_bytes(bytes))
+    }
+
+    const BOUND: Bound = Id::BOUND;
+}
+
+/// Declare a [`TypedId`] alias together with its [`IdKind`] marker.
+///
+/// ```
+/// common::typed_id!(
+///     /// Wrapper for the order id.
+///     pub OrderId, OrderIdKind, "ord"
+/// );
+///
+/// let id = OrderId::new("first-order").unwrap();
+/// assert_eq!(id.to_string(), "first-order");
+/// assert_eq!(format!("{:#}", id), "ord:first-order");
+/// ```
+#[macro_export]
+macro_rules! typed_id {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $kind:ident, $prefix:literal) => {
+        #[doc = concat!("Kind marker for [`", stringify!($name), "`].")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        $vis struct $kind;
+
+        impl $crate::id::IdKind for $kind {
+            const PREFIX: &'static str = $prefix;
+        }
+
+        $(#[$meta])*
+        $vis type $name = $crate::id::TypedId<$kind>;
+    };
+}
+
+#[cfg(test)]
+pub(crate) mod proptests {
+    use candid::{CandidType, Decode, Encode};
+    use proptest::prelude::*;
+    use serde::{de::DeserializeOwned, Serialize};
+
+    /// Proptest configuration for the id modules, with fewer cases under Miri.
+    pub(crate) fn config() -> ProptestConfig {
+        if cfg!(miri) {
+            ProptestConfig {
+                cases: 8,
+                failure_persistence: None,
+                ..ProptestConfig::default()
+            }
+        } else {
+            ProptestConfig::default()
+        }
+    }
+
+    /// Encodes `value` as JSON, bincode and Candid and returns the decoded copies, in that order.
+    pub(crate) fn codec_round_trip<T>(value: &T) -> [T; 3]
+    where
+        T: Serialize + DeserializeOwned + CandidType,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        let bin = bincode::serialize(value).unwrap();
+        let candid = Encode!(value).unwrap();
+        [
+            serde_json::from_str(&json).unwrap(),
+            bincode::deserialize(&bin).unwrap(),
+            Decode!(&candid, T).unwrap(),
+        ]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use candid::{Decode, Encode};
 
     #[test]
     fn test_id_new() {
@@ -342,4 +569,140 @@ mod tests {
         let id = Id::new("z".repeat(21)).unwrap();
         assert_eq!(id.as_slice().len(), 16);
     }
+
+    #[test]
+    fn test_id_bytes_with_inner_zero_byte() {
+        // The fifth byte holds only zero bits of 'a' and 'p'
+        let id = Id::new("abcdeap").unwrap();
+        assert_eq!(id.as_slice()[4], 0);
+        assert_eq!(Id::from_bytes(id.to_bytes()), id);
+        assert_eq!(Id::from_bytes(id.to_bytes()).to_string(), "abcdeap");
+    }
+
+    #[test]
+    fn test_id_try_from_invalid_slice() {
+        assert_eq!(Id::try_from_slice(&[1]), Err(IdError::BytesTooShort));
+        assert_eq!(Id::try_from_slice(&[1; 17]), Err(IdError::BytesTooLong));
+        // 'a' followed by an out-of-range value
+        assert_eq!(
+            Id::try_from_slice(&[0xff, 0xff]),
+            Err(IdError::InvalidBytes)
+        );
+        // Only one character
+        assert_eq!(Id::try_from_slice(&[1, 0]), Err(IdError::InvalidBytes));
+        // "-ab"
+        assert_eq!(Id::try_from_slice(&[101, 32]), Err(IdError::InvalidBytes));
+        assert_eq!(Id::try_from_slice(&[0, 0]), Ok(Id::default()));
+    }
+
+    mod proptests {
+        use super::*;
+        use crate::id::proptests::{self as util, config};
+        use proptest::prelude::*;
+
+        const ID_PATTERN: &str = "[a-zA-Z0-9][a-zA-Z0-9-]{1,19}[a-zA-Z0-9]";
+
+        proptest! {
+            #![proptest_config(config())]
+
+            #[test]
+            fn string_round_trip(s in ID_PATTERN) {
+                let id = Id::new(&s).unwrap();
+                prop_assert_eq!(id.to_string(), s.to_ascii_lowercase());
+                prop_assert_eq!(Id::new(id.to_string()).unwrap(), id);
+            }
+
+            #[test]
+            fn bytes_round_trip(s in ID_PATTERN) {
+                let id = Id::new(&s).unwrap();
+                let bytes = id.as_slice();
+                prop_assert!(bytes.len() >= Id::MIN_LENGTH_IN_BYTES);
+                prop_assert!(bytes.len() <= (s.len() * 6).div_ceil(8));
+                prop_assert_eq!(Id::try_from_slice(bytes).unwrap(), id);
+            }
+
+            #[test]
+            fn codec_round_trip(s in ID_PATTERN) {
+                let id = Id::new(&s).unwrap();
+                prop_assert_eq!(serde_json::to_string(&id).unwrap(), format!("\"{}\"", id));
+                prop_assert_eq!(util::codec_round_trip(&id), [id; 3]);
+            }
+
+            #[test]
+            fn storable_round_trip(s in ID_PATTERN) {
+                let id = Id::new(&s).unwrap();
+                let bytes = id.to_bytes();
+                prop_assert!(bytes.len() <= Id::MAX_LENGTH_IN_BYTES);
+                prop_assert_eq!(Id::from_bytes(bytes), id);
+            }
+
+            #[test]
+            fn arbitrary_str(s in "\\PC{0,32}") {
+                if let Ok(id) = Id::new(&s) {
+                    prop_assert_eq!(Id::new(id.to_string()).unwrap(), id);
+                    prop_assert_eq!(Id::try_from_slice(id.as_slice()).unwrap(), id);
+                }
+            }
+
+            #[test]
+            fn arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..20)) {
+                if let Ok(id) = Id::try_from_slice(&bytes) {
+                    prop_assert_eq!(Id::try_from_slice(id.as_slice()).unwrap(), id);
+                    if id != Id::default() {
+                        prop_assert_eq!(Id::new(id.to_string()).unwrap(), id);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_id() {
+        crate::typed_id!(TestId, TestIdKind, "tst");
+
+        let id = TestId::new("abc-123").unwrap();
+        assert_eq!(id.to_string(), "abc-123");
+        assert_eq!(format!("{:#}", id), "tst:abc-123");
+        assert_eq!(TestId::new("tst:abc-123").unwrap(), id);
+        assert_eq!("tst:abc-123".parse::<TestId>().unwrap(), id);
+        assert_eq!(TestId::try_from("abc-123").unwrap(), id);
+        assert_eq!(
+            TestId::new("mkt:abc-123"),
+            Err(IdError::InvalidCharacter(':'))
+        );
+        assert_eq!(id.id(), Id::new("abc-123").unwrap());
+    }
+
+    #[test]
+    fn test_from_legacy_stored_bytes() {
+        // Bytes of "abc-", cut short in the middle of an id, which are not canonical
+        let id = Id::new("abc-def").unwrap();
+        let legacy = &id.as_slice()[..3];
+        assert_eq!(Id::try_from_slice(legacy), Err(IdError::InvalidBytes));
+
+        let loaded = Id::from_bytes(Cow::Borrowed(legacy));
+        assert_eq!(loaded.to_bytes().as_ref(), legacy);
+    }
+
+    #[test]
+    fn test_typed_id_encoding_matches_id() {
+        crate::typed_id!(TestId, TestIdKind, "tst");
+
+        let id = Id::new("wiggle-stool").unwrap();
+        let typed_id = TestId::from(id);
+
+        assert_eq!(
+            serde_json::to_string(&typed_id).unwrap(),
+            serde_json::to_string(&id).unwrap()
+        );
+        assert_eq!(
+            bincode::serialize(&typed_id).unwrap(),
+            bincode::serialize(&id).unwrap()
+        );
+        assert_eq!(typed_id.to_bytes(), id.to_bytes());
+
+        let bytes = Encode!(&typed_id).unwrap();
+        assert_eq!(Decode!(&bytes, Id).unwrap(), id);
+        assert_eq!(Decode!(&bytes, TestId).unwrap(), typed_id);
+    }
 }
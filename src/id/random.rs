@@ -0,0 +1,313 @@
+use super::IdKind;
+use crate::util::rng::{self, EntropyPool, RngError};
+use candid::{
+    types::{Serializer, Type},
+    CandidType,
+};
+use ic_stable_structures::{storable::Bound, Storable};
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+const LENGTH_IN_BYTES: usize = 16;
+
+/// Crockford's base32 alphabet
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Opaque, time-sortable 128-bit ID in the ULID layout.
+///
+/// The upper 48 bits are the creation time in milliseconds since the Unix epoch and the lower
+/// 80 bits are random, so IDs of the same kind sort by creation time.
+/// IDs are displayed as 26 characters of Crockford's base32, and `{:#}` adds the kind prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct RandomId<K: IdKind>(u128, PhantomData<K>);
+
+impl<K: IdKind> RandomId<K> {
+    /// The length of an ID in characters
+    pub const LENGTH: usize = 26;
+    /// The length of an ID in bytes
+    pub const LENGTH_IN_BYTES: usize = LENGTH_IN_BYTES;
+
+    const TIMESTAMP_BITS: u32 = 48;
+    const RANDOM_BITS: u32 = 80;
+    const RANDOM_MASK: u128 = (1 << Self::RANDOM_BITS) - 1;
+
+    /// Create an ID from its timestamp and random part.
+    /// Bits beyond 48 bits of the timestamp and 80 bits of the random part are discarded.
+    pub fn from_parts(timestamp_ms: u64, random: u128) -> Self {
+        let timestamp = (timestamp_ms as u128) & ((1 << Self::TIMESTAMP_BITS) - 1);
+        Self(
+            (timestamp << Self::RANDOM_BITS) | (random & Self::RANDOM_MASK),
+            PhantomData,
+        )
+    }
+
+    /// Generate an ID for the current canister time from the canister-wide entropy pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool has not been seeded with [`rng::ensure_seeded`].
+    pub fn generate() -> Result<Self, RngError> {
+        let timestamp_ms = ic_cdk::api::time() / 1_000_000;
+        rng::with_pool(|pool| Self::generate_with(timestamp_ms, pool))
+    }
+
+    /// Generate an ID for the given time from the given entropy pool
+    pub fn generate_with(timestamp_ms: u64, pool: &mut EntropyPool) -> Result<Self, RngError> {
+        let mut random = [0u8; LENGTH_IN_BYTES];
+        pool.fill_bytes(&mut random[..(Self::RANDOM_BITS / 8) as usize])?;
+        Ok(Self::from_parts(timestamp_ms, u128::from_le_bytes(random)))
+    }
+
+    /// Get the creation time in milliseconds since the Unix epoch
+    pub fn timestamp_ms(&self) -> u64 {
+        (self.0 >> Self::RANDOM_BITS) as u64
+    }
+
+    /// Get the ID as a number
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+
+    fn from_str_core(s: &str) -> Result<Self, RandomIdError> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix(K::PREFIX)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .unwrap_or(s);
+
+        if s.chars().count() != Self::LENGTH {
+            return Err(RandomIdError::InvalidLength);
+        }
+
+        let mut value: u128 = 0;
+        for (position, c) in s.chars().enumerate() {
+            let digit = match c.to_ascii_uppercase() {
+                'O' => 0,
+                'I' | 'L' => 1,
+                upper => match ALPHABET.iter().position(|&a| a as char == upper) {
+                    Some(digit) => digit as u128,
+                    None => return Err(RandomIdError::InvalidCharacter(c)),
+                },
+            };
+            // 26 characters hold 130 bits, so the first one may only use its lower 3 bits
+            if position == 0 && digit > 7 {
+                return Err(RandomIdError::Overflow);
+            }
+            value = (value << 5) | digit;
+        }
+
+        Ok(Self(value, PhantomData))
+    }
+}
+
+impl<K: IdKind> fmt::Display for RandomId<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = String::with_capacity(Self::LENGTH + K::PREFIX.len() + 1);
+        if f.alternate() {
+            result.push_str(K::PREFIX);
+            result.push(':');
+        }
+        for position in (0..Self::LENGTH).rev() {
+            let digit = (self.0 >> (position * 5)) & 0b1_1111;
+            result.push(ALPHABET[digit as usize] as char);
+        }
+        f.write_str(&result)
+    }
+}
+
+impl<K: IdKind> std::str::FromStr for RandomId<K> {
+    type Err = RandomIdError;
+
+    /// Parse a base32 string, with or without the kind prefix, into an ID
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
+    }
+}
+
+impl<K: IdKind> TryFrom<&str> for RandomId<K> {
+    type Error = RandomIdError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
+    }
+}
+
+impl<K: IdKind> From<u128> for RandomId<K> {
+    fn from(value: u128) -> Self {
+        Self(value, PhantomData)
+    }
+}
+
+impl<K: IdKind> From<RandomId<K>> for u128 {
+    fn from(id: RandomId<K>) -> Self {
+        id.0
+    }
+}
+
+// IDs are text in every format but `Storable`, so that frontends never handle 128-bit numbers.
+impl<K: IdKind> CandidType for RandomId<K> {
+    fn _ty() -> Type {
+        String::ty()
+    }
+
+    fn idl_serialize<S: Serializer>(&self, serializer: S) -> Result<(), S::Error> {
+        serializer.serialize_text(&self.to_string())
+    }
+}
+
+impl<K: IdKind> serde::Serialize for RandomId<K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, K: IdKind> serde::Deserialize<'de> for RandomId<K> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let s = String::deserialize(deserializer)?;
+        Self::from_str_core(&s).map_err(D::Error::custom)
+    }
+}
+
+impl<K: IdKind> Storable for RandomId<K> {
+    /// Big-endian bytes, so that stored IDs keep their time order
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(self.0.to_be_bytes().to_vec())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Self(
+            u128::from_be_bytes(bytes.as_ref().try_into().unwrap()),
+            PhantomData,
+        )
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: LENGTH_IN_BYTES as u32,
+        is_fixed_size: true,
+    };
+}
+
+/// Declare a [`RandomId`] alias together with its [`IdKind`] marker.
+///
+/// Works the same as [`typed_id!`](crate::typed_id).
+#[macro_export]
+macro_rules! random_id {
+    ($(#[$meta:meta])* $vis:vis $name:ident, $kind:ident, $prefix:literal) => {
+        #[doc = concat!("Kind marker for [`", stringify!($name), "`].")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        $vis struct $kind;
+
+        impl $crate::id::IdKind for $kind {
+            const PREFIX: &'static str = $prefix;
+        }
+
+        $(#[$meta])*
+        $vis type $name = $crate::id::RandomId<$kind>;
+    };
+}
+
+crate::random_id!(
+    /// Random id for orders.
+    pub OrderId, OrderIdKind, "ord"
+);
+
+crate::random_id!(
+    /// Random id for payments.
+    pub PaymentId, PaymentIdKind, "pay"
+);
+
+crate::random_id!(
+    /// Random id for sessions.
+    pub SessionId, SessionIdKind, "ses"
+);
+
+crate::random_id!(
+    /// Random id for carts.
+    pub CartId, CartIdKind, "crt"
+);
+
+/// Errors that can occur when parsing random IDs
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum RandomIdError {
+    #[error("Random ID must be 26 characters long.")]
+    InvalidLength,
+
+    #[error("Invalid character '{0}' in random ID.")]
+    InvalidCharacter(char),
+
+    #[error("Random ID is larger than 128 bits.")]
+    Overflow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_id_parse() {
+        // Example from the ULID specification
+        let id = OrderId::try_from("01ARZ3NDEKTSV4RRFFQ69G5FAV").unwrap();
+        assert_eq!(id.timestamp_ms(), 1469922850259);
+        assert_eq!(id.to_string(), "01ARZ3NDEKTSV4RRFFQ69G5FAV");
+        assert_eq!(format!("{:#}", id), "ord:01ARZ3NDEKTSV4RRFFQ69G5FAV");
+        assert_eq!(
+            "ord:01arz3ndektsv4rrffq69g5fav".parse::<OrderId>().unwrap(),
+            id
+        );
+        assert_eq!(
+            "0LARZ3NDEKTSV4RRFFQ69G5FAV".parse::<OrderId>().unwrap(),
+            "01ARZ3NDEKTSV4RRFFQ69G5FAV".parse::<OrderId>().unwrap()
+        );
+
+        assert_eq!(
+            OrderId::try_from("01ARZ"),
+            Err(RandomIdError::InvalidLength)
+        );
+        assert_eq!(
+            OrderId::try_from("01ARZ3NDEKTSV4RRFFQ69G5FAU"),
+            Err(RandomIdError::InvalidCharacter('U'))
+        );
+        assert_eq!(
+            OrderId::try_from("81ARZ3NDEKTSV4RRFFQ69G5FAV"),
+            Err(RandomIdError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_random_id_generate() {
+        let mut pool = EntropyPool::new();
+        assert_eq!(
+            OrderId::generate_with(1, &mut pool),
+            Err(RngError::NotSeeded)
+        );
+
+        pool.seed([7; 32]);
+        let first = OrderId::generate_with(1_700_000_000_000, &mut pool).unwrap();
+        let second = OrderId::generate_with(1_700_000_000_000, &mut pool).unwrap();
+        let later = OrderId::generate_with(1_700_000_000_001, &mut pool).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(first.timestamp_ms(), 1_700_000_000_000);
+        assert!(first < later && second < later);
+        assert_eq!(
+            OrderId::try_from(first.to_string().as_str()).unwrap(),
+            first
+        );
+    }
+
+    #[test]
+    fn test_random_id_encoding() {
+        let id = CartId::from_parts(1_700_000_000_000, 42);
+
+        assert_eq!(CartId::from_bytes(id.to_bytes()), id);
+        assert_eq!(id.to_bytes().len(), 16);
+        assert!(CartId::from_parts(1, 0).to_bytes() < CartId::from_parts(2, 0).to_bytes());
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id));
+        assert_eq!(serde_json::from_str::<CartId>(&json).unwrap(), id);
+
+        let bin = bincode::serialize(&id).unwrap();
+        assert_eq!(bincode::deserialize::<CartId>(&bin).unwrap(), id);
+    }
+}
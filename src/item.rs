@@ -1,8 +1,10 @@
 use crate::{
     market::{MarketId, MarketName},
-    media::MediaData,
+    media::{AccessibilityIssue, MediaData, MediaDataError},
     store::{StoreId, StoreName},
+    typed_id,
     unit::{Currency, Price},
+    util::locale::{LanguageTag, LocalizedText},
 };
 use candid::{CandidType, Decode, Encode};
 use ic_cdk::api::call::RejectionCode;
@@ -11,17 +13,20 @@ use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, collections::BTreeMap};
 
 pub mod attr;
-mod id;
 mod key;
 pub mod spec;
 pub mod tag;
 
 use attr::{AttrIndexesResponse, AttrKeys, AttrRequest, AttrStatusesResponse, Stock};
-pub use id::ItemId;
-pub use key::ItemKey;
+pub use key::{ItemKey, ItemKeyError};
 use spec::SpecResponse;
 pub use tag::{Tag, TagError};
 
+typed_id!(
+    /// Wrapper for the item id.
+    pub ItemId, ItemIdKind, "itm"
+);
+
 pub type ItemName = String;
 
 /// Type of the item.
@@ -46,6 +51,8 @@ pub struct ItemPageRequest {
     pub market_id: MarketId,
     pub store_id: StoreId,
     pub request_to_store: ItemPageRequestToStoreCanister,
+    /// Languages of the shopper, most preferred first, absent in requests from older clients
+    pub languages: Option<Vec<LanguageTag>>,
 }
 
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
@@ -65,16 +72,19 @@ pub struct ItemPageResponse {
     pub specs: Option<SpecResponse>,
     pub fallback_attr: Option<AttrKeys>,
 }
+/// Combine the response of the store canister with the market data, keeping only the texts
+/// in the most preferred of `languages`.
 pub fn get_item_page_response(
     item_page_response: ItemPageResponseFromStoreCanister,
-    market_name: MarketName,
+    market_name: &LocalizedText<MarketName>,
+    languages: &[LanguageTag],
 ) -> ItemPageResponse {
     ItemPageResponse {
         static_data: item_page_response
             .static_data
             .map(|static_data| ItemPageStaticData {
-                market_name,
-                data_from_store: static_data,
+                market_name: market_name.localize(languages),
+                data_from_store: static_data.localize(languages),
             }),
         price: item_page_response.price,
         images: item_page_response.images,
@@ -98,17 +108,33 @@ pub struct ItemPageResponseFromStoreCanister {
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
 pub struct ItemPageStaticData {
-    pub market_name: MarketName,
+    pub market_name: LocalizedText<MarketName>,
     pub data_from_store: ItemPageStaticDataFromStoreCanister,
 }
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
 pub struct ItemPageStaticDataFromStoreCanister {
-    pub item_name: ItemName,
-    pub descriptions: Vec<String>,
+    pub item_name: LocalizedText<ItemName>,
+    pub descriptions: Vec<LocalizedText>,
     pub tags: Vec<Tag>,
     pub attrs: AttrIndexesResponse,
-    pub store_name: StoreName,
+    pub store_name: LocalizedText<StoreName>,
+}
+
+impl ItemPageStaticDataFromStoreCanister {
+    /// Keep only the texts in the most preferred of `languages`
+    pub fn localize(self, languages: &[LanguageTag]) -> Self {
+        Self {
+            item_name: self.item_name.localize(languages),
+            descriptions: self
+                .descriptions
+                .iter()
+                .map(|description| description.localize(languages))
+                .collect(),
+            store_name: self.store_name.localize(languages),
+            ..self
+        }
+    }
 }
 
 #[derive(CandidType, Copy, Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -159,9 +185,19 @@ pub struct MediaDataWithCaption {
 }
 
 impl MediaDataWithCaption {
+    /// The maximum length of the caption in characters
+    pub const MAX_CAPTION_LENGTH: usize = 1000;
+
     pub fn builder() -> MediaDataWithCaptionBuilder {
         MediaDataWithCaptionBuilder::default()
     }
+
+    pub fn validate(&self) -> Result<(), MediaDataError> {
+        if self.caption.chars().count() > Self::MAX_CAPTION_LENGTH {
+            return Err(MediaDataError::CaptionTooLong);
+        }
+        self.data.validate()
+    }
 }
 
 #[derive(Default)]
@@ -181,14 +217,45 @@ impl MediaDataWithCaptionBuilder {
         self
     }
 
-    pub fn build(self) -> MediaDataWithCaption {
-        MediaDataWithCaption {
-            data: self.data.unwrap(),
+    pub fn try_build(self) -> Result<MediaDataWithCaption, MediaDataError> {
+        let data = MediaDataWithCaption {
+            data: self
+                .data
+                .ok_or_else(|| MediaDataError::MissingField("data".to_string()))?,
             caption: self.caption.unwrap_or_default(),
-        }
+        };
+        data.validate()?;
+        Ok(data)
     }
 }
 
+/// Accessibility problem of an item image, by index
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct MediaAccessibilityViolation {
+    pub index: u32,
+    pub issue: AccessibilityIssue,
+}
+
+/// List the accessibility problems of the images of an item, e.g. before publishing it
+pub fn audit_media_accessibility(
+    images: &[MediaDataWithCaption],
+) -> Vec<MediaAccessibilityViolation> {
+    images
+        .iter()
+        .enumerate()
+        .flat_map(|(index, image)| {
+            image
+                .data
+                .accessibility_issues()
+                .into_iter()
+                .map(move |issue| MediaAccessibilityViolation {
+                    index: index as u32,
+                    issue,
+                })
+        })
+        .collect()
+}
+
 #[derive(
     CandidType, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialOrd, Ord, PartialEq,
 )]
@@ -286,3 +353,72 @@ pub fn get_item_glance_data(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::mime::{Mime, MimeImage, MimeVideo};
+
+    #[test]
+    fn test_media_data_with_caption_try_build() {
+        assert_eq!(
+            MediaDataWithCaption::builder().caption("Front").try_build(),
+            Err(MediaDataError::MissingField("data".to_string()))
+        );
+
+        let data = MediaData::builder()
+            .url("https://anthol.net/a.png")
+            .mime(Mime::Image(MimeImage::Png))
+            .alt("Red mug")
+            .try_build()
+            .unwrap();
+        let image = MediaDataWithCaption::builder()
+            .data(data.clone())
+            .try_build()
+            .unwrap();
+        assert_eq!(image.caption, "");
+        assert_eq!(
+            MediaDataWithCaption::builder()
+                .data(data)
+                .caption(&"a".repeat(MediaDataWithCaption::MAX_CAPTION_LENGTH + 1))
+                .try_build(),
+            Err(MediaDataError::CaptionTooLong)
+        );
+    }
+
+    #[test]
+    fn test_audit_media_accessibility() {
+        let image = |data: MediaData| MediaDataWithCaption {
+            data,
+            caption: String::new(),
+        };
+        let png = MediaData::builder()
+            .url("https://anthol.net/a.png")
+            .mime(Mime::Image(MimeImage::Png))
+            .alt("Red mug")
+            .try_build()
+            .unwrap();
+        let mut unlabeled = png.clone();
+        unlabeled.alt = None;
+        let video = MediaData::builder()
+            .url("https://anthol.net/a.mp4")
+            .mime(Mime::Video(MimeVideo::Mp4))
+            .try_build()
+            .unwrap();
+
+        assert_eq!(audit_media_accessibility(&[image(png.clone())]), vec![]);
+        assert_eq!(
+            audit_media_accessibility(&[image(png), image(unlabeled), image(video)]),
+            vec![
+                MediaAccessibilityViolation {
+                    index: 1,
+                    issue: AccessibilityIssue::MissingAlt
+                },
+                MediaAccessibilityViolation {
+                    index: 2,
+                    issue: AccessibilityIssue::MissingCaptions
+                },
+            ]
+        );
+    }
+}
@@ -1,67 +1,209 @@
+use crate::util::rng::{self, EntropyPool, RngError};
 use candid::CandidType;
-use derive_more::{AsRef, Display, From, Into};
-use ic_cdk::api::{call::CallResult, management_canister::main::raw_rand};
+use derive_more::{AsRef, From, Into};
 use ic_stable_structures::{storable::Bound, Storable};
-use serde::{Deserialize, Serialize};
+use std::fmt;
 
+/// Crockford's base32 alphabet followed by the 5 extra check symbols
+const ALPHABET: &[u8; 37] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ*~$=U";
+
+/// Random key of an item.
+///
+/// Keys are displayed as 13 characters of Crockford's base32 followed by a mod 37 check symbol,
+/// e.g. `88CAE7G8KXY0F8`, and `{:#}` groups them by 4 characters, e.g. `88CA-E7G8-KXY0-F8`.
+/// Parsing is case-insensitive, ignores hyphens and reads `I`/`L` as `1` and `O` as `0`.
 #[derive(
-    CandidType,
-    Clone,
-    Copy,
-    Serialize,
-    Deserialize,
-    Debug,
-    Hash,
-    Eq,
-    PartialEq,
-    PartialOrd,
-    Ord,
-    Default,
-    AsRef,
-    From,
-    Display,
-    Into,
+    CandidType, Clone, Copy, Debug, Hash, Eq, PartialEq, PartialOrd, Ord, Default, AsRef, From, Into,
 )]
 pub struct ItemKey(u64);
 
 impl ItemKey {
-    pub async fn create() -> CallResult<Self> {
-        Ok(Self(Self::get_random_u64().await?))
+    /// The length of an encoded key in characters, including the check symbol
+    pub const LENGTH: usize = 14;
+
+    const DATA_LENGTH: usize = Self::LENGTH - 1;
+    /// Number of random bytes drawn for a key
+    const BYTES: usize = std::mem::size_of::<u64>();
+    const GROUP_SIZE: usize = 4;
+
+    /// Create a key, seeding the canister-wide entropy pool from `raw_rand` first if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeding the pool fails.
+    pub async fn create() -> Result<Self, RngError> {
+        rng::ensure_seeded(Self::BYTES).await?;
+        Self::generate()
+    }
+
+    /// Create four keys, seeding the canister-wide entropy pool from `raw_rand` first if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeding the pool fails.
+    pub async fn create_four() -> Result<[Self; 4], RngError> {
+        rng::ensure_seeded(4 * Self::BYTES).await?;
+        rng::with_pool(|pool| {
+            Ok([
+                Self::generate_with(pool)?,
+                Self::generate_with(pool)?,
+                Self::generate_with(pool)?,
+                Self::generate_with(pool)?,
+            ])
+        })
+    }
+
+    /// Generate a key from the canister-wide entropy pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool has not been seeded with [`rng::ensure_seeded`].
+    pub fn generate() -> Result<Self, RngError> {
+        rng::with_pool(Self::generate_with)
+    }
+
+    /// Generate any number of keys from the canister-wide entropy pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool has not been seeded with [`rng::ensure_seeded`].
+    pub fn generate_many(count: usize) -> Result<Vec<Self>, RngError> {
+        rng::with_pool(|pool| (0..count).map(|_| Self::generate_with(pool)).collect())
     }
 
-    pub async fn create_four() -> CallResult<[Self; 4]> {
-        let random_u64s = Self::get_four_random_u64().await?;
-        Ok([
-            Self(random_u64s[0]),
-            Self(random_u64s[1]),
-            Self(random_u64s[2]),
-            Self(random_u64s[3]),
-        ])
+    /// Generate a key from the given entropy pool
+    pub fn generate_with(pool: &mut EntropyPool) -> Result<Self, RngError> {
+        Ok(Self(pool.next_u64()?))
     }
 
-    async fn get_random_u64() -> CallResult<u64> {
-        let random_bytes = Self::get_random_32_bytes().await?;
-        let mut random_u64 = 0u64;
-        for (i, &byte) in random_bytes.iter().take(8).enumerate() {
-            random_u64 |= (byte as u64) << (i * 8);
+    fn check_symbol(&self) -> char {
+        ALPHABET[(self.0 % 37) as usize] as char
+    }
+
+    fn from_str_core(s: &str) -> Result<Self, ItemKeyError> {
+        let chars: Vec<char> = s.trim().chars().filter(|&c| c != '-').collect();
+        if chars.len() != Self::LENGTH {
+            return Err(ItemKeyError::InvalidLength);
         }
 
-        Ok(random_u64)
+        let mut value: u64 = 0;
+        for (position, &c) in chars[..Self::DATA_LENGTH].iter().enumerate() {
+            let digit = match Self::symbol_value(c) {
+                Some(digit) if digit < 32 => digit,
+                _ => return Err(ItemKeyError::InvalidCharacter(c)),
+            };
+            // 13 characters hold 65 bits, so the first one may only use its lower 4 bits
+            if position == 0 && digit > 15 {
+                return Err(ItemKeyError::Overflow);
+            }
+            value = (value << 5) | digit;
+        }
+
+        let check = chars[Self::DATA_LENGTH];
+        match Self::symbol_value(check) {
+            Some(check_value) if check_value == value % 37 => Ok(Self(value)),
+            Some(_) => Err(ItemKeyError::ChecksumMismatch),
+            None => Err(ItemKeyError::InvalidCharacter(check)),
+        }
     }
 
-    async fn get_four_random_u64() -> CallResult<[u64; 4]> {
-        let random_bytes = Self::get_random_32_bytes().await?;
-        let mut random_u64 = [0u64; 4];
-        for (i, &byte) in random_bytes.iter().enumerate() {
-            random_u64[i / 8] |= (byte as u64) << ((i % 8) * 8);
+    fn symbol_value(c: char) -> Option<u64> {
+        match c.to_ascii_uppercase() {
+            'O' => Some(0),
+            'I' | 'L' => Some(1),
+            upper => ALPHABET
+                .iter()
+                .position(|&a| a as char == upper)
+                .map(|value| value as u64),
+        }
+    }
+}
+
+impl fmt::Display for ItemKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = String::with_capacity(Self::LENGTH + Self::LENGTH / Self::GROUP_SIZE);
+        let symbols = (0..Self::DATA_LENGTH)
+            .rev()
+            .map(|position| ALPHABET[((self.0 >> (position * 5)) & 0b1_1111) as usize] as char)
+            .chain(std::iter::once(self.check_symbol()));
+
+        for (position, c) in symbols.enumerate() {
+            if f.alternate() && position > 0 && position % Self::GROUP_SIZE == 0 {
+                result.push('-');
+            }
+            result.push(c);
         }
 
-        Ok(random_u64)
+        f.write_str(&result)
+    }
+}
+
+impl std::str::FromStr for ItemKey {
+    type Err = ItemKeyError;
+
+    /// Parse an encoded key, with or without grouping hyphens
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
+    }
+}
+
+impl TryFrom<&str> for ItemKey {
+    type Error = ItemKeyError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
     }
+}
 
-    async fn get_random_32_bytes() -> CallResult<[u8; 32]> {
-        let (random_bytes,) = raw_rand().await?;
-        Ok(random_bytes.as_slice().try_into().unwrap())
+// Serialization
+impl serde::Serialize for ItemKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u64(self.0)
+        }
+    }
+}
+
+// Deserialization
+mod deserialize {
+    use super::ItemKey;
+
+    // Visitor for the encoded string. Plain numbers are accepted as well, as keys used to be
+    // serialized as numbers in every format.
+    pub(super) struct ItemKeyVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for ItemKeyVisitor {
+        type Value = ItemKey;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            formatter.write_str("encoded item key or u64")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            ItemKey::from_str_core(v).map_err(E::custom)
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(ItemKey(v))
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ItemKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<ItemKey, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(deserialize::ItemKeyVisitor)
+        } else {
+            deserializer.deserialize_u64(deserialize::ItemKeyVisitor)
+        }
     }
 }
 
@@ -80,6 +222,25 @@ impl Storable for ItemKey {
     };
 }
 
+/// Errors that can occur when parsing item keys
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ItemKeyError {
+    #[error(
+        "Item key must be {} characters long without hyphens.",
+        ItemKey::LENGTH
+    )]
+    InvalidLength,
+
+    #[error("Invalid character '{0}' in item key.")]
+    InvalidCharacter(char),
+
+    #[error("Item key is larger than 64 bits.")]
+    Overflow,
+
+    #[error("Item key does not match its check symbol.")]
+    ChecksumMismatch,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +259,86 @@ mod tests {
         let bytes = key.to_bytes();
         assert_eq!(bytes.len(), 8);
     }
+
+    #[test]
+    fn test_item_key_generate_with_seed() {
+        let mut pool = EntropyPool::with_seed([1; 32]);
+        let mut same_pool = EntropyPool::with_seed([1; 32]);
+        let mut other_pool = EntropyPool::with_seed([2; 32]);
+
+        let keys: Vec<ItemKey> = (0..100)
+            .map(|_| ItemKey::generate_with(&mut pool).unwrap())
+            .collect();
+        let same_keys: Vec<ItemKey> = (0..100)
+            .map(|_| ItemKey::generate_with(&mut same_pool).unwrap())
+            .collect();
+
+        assert_eq!(keys, same_keys);
+        assert_ne!(keys[0], ItemKey::generate_with(&mut other_pool).unwrap());
+        assert!(keys.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn test_item_key_generate_unseeded() {
+        let mut pool = EntropyPool::new();
+        assert_eq!(ItemKey::generate_with(&mut pool), Err(RngError::NotSeeded));
+    }
+
+    #[test]
+    fn test_item_key_encoding() {
+        let key = ItemKey(9525480706759653391);
+        assert_eq!(key.to_string(), "88CAE7G8KXY0F8");
+        assert_eq!(format!("{:#}", key), "88CA-E7G8-KXY0-F8");
+        assert_eq!(ItemKey(0).to_string(), "00000000000000");
+        assert_eq!(ItemKey(u64::MAX).to_string(), "FZZZZZZZZZZZZB");
+
+        assert_eq!("88CAE7G8KXY0F8".parse::<ItemKey>().unwrap(), key);
+        assert_eq!("88ca-e7g8-kxyo-f8".parse::<ItemKey>().unwrap(), key);
+        assert_eq!(
+            ItemKey::try_from(ItemKey(u64::MAX).to_string().as_str()).unwrap(),
+            ItemKey(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_item_key_encoding_errors() {
+        assert_eq!(
+            "88CAE7G8KXY0F".parse::<ItemKey>(),
+            Err(ItemKeyError::InvalidLength)
+        );
+        assert_eq!(
+            "88CAE7G8KXU0F8".parse::<ItemKey>(),
+            Err(ItemKeyError::InvalidCharacter('U'))
+        );
+        assert_eq!(
+            "G0000000000000".parse::<ItemKey>(),
+            Err(ItemKeyError::Overflow)
+        );
+        // Single substitution and adjacent transposition
+        assert_eq!(
+            "88CAE7G8KXY1F8".parse::<ItemKey>(),
+            Err(ItemKeyError::ChecksumMismatch)
+        );
+        assert_eq!(
+            "88CAE7G8KYX0F8".parse::<ItemKey>(),
+            Err(ItemKeyError::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn test_item_key_serde() {
+        let key = ItemKey(9525480706759653391);
+
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, "\"88CAE7G8KXY0F8\"");
+        assert_eq!(serde_json::from_str::<ItemKey>(&json).unwrap(), key);
+        assert_eq!(
+            serde_json::from_str::<ItemKey>("9525480706759653391").unwrap(),
+            key
+        );
+
+        let bin = bincode::serialize(&key).unwrap();
+        assert_eq!(bin, 9525480706759653391u64.to_le_bytes());
+        assert_eq!(bincode::deserialize::<ItemKey>(&bin).unwrap(), key);
+    }
 }
@@ -1,6 +1,10 @@
 use crate::{
-    id::{Id, IdError},
     item::{ItemCoreKeys, ItemGlanceData},
+    typed_id,
+    util::{
+        locale::LocalizedText,
+        name::{normalize_display_name, normalize_name, NameError},
+    },
 };
 use candid::{CandidType, Principal};
 use derive_more::{AsRef, Display, From, Into};
@@ -27,10 +31,34 @@ use std::str::FromStr;
 )]
 pub struct MarketPrincipal(Principal);
 
+typed_id!(
+    /// Wrapper for the market id.
+    pub MarketId, MarketIdKind, "mkt"
+);
+
+impl Storable for MarketPrincipal {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        self.0.to_bytes()
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Self(Principal::from_bytes(bytes))
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Principal::MAX_LENGTH_IN_BYTES as u32,
+        is_fixed_size: false,
+    };
+}
+
+/// Market name up to 50 characters.
+///
+/// Names are validated by [`normalize_display_name`] on creation.
+/// Decoding is not validated, so that names stored as plain strings before validation existed
+/// keep decoding.
 #[derive(
     CandidType,
     Clone,
-    Copy,
     Serialize,
     Deserialize,
     Debug,
@@ -41,88 +69,130 @@ pub struct MarketPrincipal(Principal);
     Ord,
     Default,
     AsRef,
-    From,
     Display,
+    Into,
 )]
-pub struct MarketId(Id);
+pub struct MarketName(String);
+
+impl MarketName {
+    /// The maximum length of a name in grapheme clusters
+    pub const MAX_LENGTH: usize = 50;
+    /// The maximum length of a name in bytes
+    pub const MAX_LENGTH_IN_BYTES: usize = 200;
 
-impl MarketId {
-    pub fn new<T: AsRef<str>>(id: T) -> Result<Self, IdError> {
-        Ok(MarketId(Id::new(id)?))
+    pub fn new<T: AsRef<str>>(name: T) -> Result<Self, MarketNameError> {
+        Self::from_str_core(name.as_ref())
     }
-}
 
-impl FromStr for MarketId {
-    type Err = IdError;
+    /// Create a name for one of the platform's own markets, which may use a reserved name
+    pub fn new_for_platform<T: AsRef<str>>(name: T) -> Result<Self, MarketNameError> {
+        let name = normalize_name(name.as_ref(), Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(MarketName(name))
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(MarketId(Id::from_str(s)?))
+    fn from_str_core(s: &str) -> Result<Self, MarketNameError> {
+        let name = normalize_display_name(s, Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(MarketName(name))
     }
 }
 
-impl TryFrom<&str> for MarketId {
-    type Error = IdError;
+impl FromStr for MarketName {
+    type Err = MarketNameError;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(MarketId(Id::from_str(value)?))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
     }
 }
 
-impl TryFrom<String> for MarketId {
-    type Error = IdError;
+impl TryFrom<&str> for MarketName {
+    type Error = MarketNameError;
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        Ok(MarketId(Id::from_str(&value)?))
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
     }
 }
 
-impl Storable for MarketPrincipal {
-    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
-        self.0.to_bytes()
-    }
+impl TryFrom<String> for MarketName {
+    type Error = MarketNameError;
 
-    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Self(Principal::from_bytes(bytes))
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str_core(&value)
     }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: Principal::MAX_LENGTH_IN_BYTES as u32,
-        is_fixed_size: false,
-    };
 }
 
-impl Storable for MarketId {
+impl Storable for MarketName {
     fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
         self.0.to_bytes()
     }
 
     fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Self(Id::from_bytes(bytes))
+        Self(String::from_bytes(bytes))
     }
 
     const BOUND: Bound = Bound::Bounded {
-        max_size: 16,
+        max_size: Self::MAX_LENGTH_IN_BYTES as u32,
         is_fixed_size: false,
     };
 }
 
-pub type MarketName = String;
+pub type MarketNameError = NameError;
 
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
 pub struct MarketDataResponse {
-    pub name: MarketName,
+    pub name: LocalizedText<MarketName>,
     pub items: Vec<ItemCoreKeys>,
 }
 
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
 pub struct MarketDataResponseWithItemGlances {
     pub id: MarketId,
-    pub name: MarketName,
+    pub name: LocalizedText<MarketName>,
     pub items: Vec<ItemGlanceData>,
 }
 
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
 pub struct MarketInitArg {
     pub id: MarketId,
-    pub name: MarketName,
+    pub name: LocalizedText<MarketName>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+
+    #[test]
+    fn test_market_name() {
+        let name = MarketName::new("test").unwrap();
+        assert_eq!(name.to_string(), "test");
+        assert_eq!(MarketName::from_str("test").unwrap(), name);
+        assert_eq!(MarketName::try_from("test").unwrap(), name);
+        assert_eq!(MarketName::try_from("test".to_string()).unwrap(), name);
+        assert_eq!(MarketName::from_bytes(name.to_bytes()), name);
+
+        let name = MarketName::new(" Anthol\tMarket\n").unwrap();
+        assert_eq!(name.to_string(), "Anthol Market");
+        assert_eq!(MarketName::new("  "), Err(MarketNameError::Empty));
+        assert_eq!(
+            MarketName::new("a".repeat(51)),
+            Err(MarketNameError::TooLong)
+        );
+        assert_eq!(MarketName::new("Admin"), Err(MarketNameError::Reserved));
+    }
+
+    #[test]
+    fn test_market_name_decodes_plain_strings() {
+        let name: MarketName = serde_json::from_str("\"Anthol Market\"").unwrap();
+        assert_eq!(name, MarketName::new("Anthol Market").unwrap());
+        assert_eq!(serde_json::to_string(&name).unwrap(), "\"Anthol Market\"");
+
+        let bin = bincode::serialize(&"Anthol Market".to_string()).unwrap();
+        assert_eq!(bincode::deserialize::<MarketName>(&bin).unwrap(), name);
+
+        let bytes = Encode!(&name).unwrap();
+        assert_eq!(
+            LocalizedText::<MarketName>::decode_or_migrate(&bytes).unwrap(),
+            LocalizedText::from(name)
+        );
+    }
 }
@@ -1,28 +1,104 @@
-use candid::CandidType;
+use crate::util::network::DfxNetwork;
+use candid::{CandidType, Decode, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use url::Url;
 
+mod accessibility;
+pub mod ipfs;
+mod metadata;
 pub mod mime;
+mod responsive;
 
-use mime::Mime;
+pub use accessibility::{
+    AccessibilityIssue, MediaAccessibility, TextTrack, TextTrackKind, Transcript, TranscriptContent,
+};
+use ipfs::{Cid, CidError, IpfsGatewayConfig};
+pub use metadata::{MediaMetadata, MediaMetadataError, Placeholder};
+use mime::{Codec, ContentType, Mime, MimeDetectionError};
+pub use responsive::{ImageSizes, ImageVariant, PictureSource};
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct MediaData {
     pub src: MediaSrc,
     pub mime: Mime,
+    /// Codecs inside the container of a video or audio, if known
+    pub codecs: Option<Vec<Codec>>,
     pub alt: Option<String>,
+    /// Purely decorative media, which needs no alt text, captions or transcript
+    pub decorative: Option<bool>,
+    /// Resized or re-encoded copies of an image
+    pub variants: Option<Vec<ImageVariant>>,
+    pub metadata: Option<MediaMetadata>,
+    pub accessibility: Option<MediaAccessibility>,
+    /// Image shown before a video plays or a model or document loads
+    #[serde(default)]
+    pub poster: Option<Box<MediaData>>,
 }
 
 impl MediaData {
+    /// The maximum length of the alt text in characters
+    pub const MAX_ALT_LENGTH: usize = 500;
+
     pub fn builder() -> MediaDataBuilder {
         MediaDataBuilder::default()
     }
+
+    /// Check whether the media is marked decorative
+    pub fn is_decorative(&self) -> bool {
+        self.decorative.unwrap_or(false)
+    }
+
+    /// Check whether the media needs alt text unless it is decorative, as images and 3D models do
+    pub fn needs_alt(&self) -> bool {
+        matches!(self.mime, Mime::Image(_) | Mime::Model(_))
+    }
+
+    /// Check that an image or model has alt text unless it is decorative, that the poster is a
+    /// valid image, and that the metadata is valid
+    pub fn validate(&self) -> Result<(), MediaDataError> {
+        let alt = self.alt.as_deref().map(str::trim).unwrap_or_default();
+        if alt.is_empty() && self.needs_alt() && !self.is_decorative() {
+            return Err(MediaDataError::MissingAlt);
+        }
+        if alt.chars().count() > Self::MAX_ALT_LENGTH {
+            return Err(MediaDataError::AltTooLong);
+        }
+        if let Some(poster) = &self.poster {
+            if !matches!(poster.mime, Mime::Image(_)) || poster.poster.is_some() {
+                return Err(MediaDataError::InvalidPoster);
+            }
+            poster.validate()?;
+        }
+        match &self.metadata {
+            Some(metadata) => metadata.validate().map_err(MediaDataError::InvalidMetadata),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that the uploaded file is of the declared MIME type
+    pub fn verify(&self, filename: &str, bytes: &[u8]) -> Result<(), MimeDetectionError> {
+        self.mime.verify(filename, bytes).map(|_| ())
+    }
+
+    /// Get the MIME type with the codecs, e.g. for the `type` attribute of `<source>`
+    pub fn content_type(&self) -> ContentType {
+        ContentType::from(self.mime.clone()).with_codecs(self.codecs.as_deref().unwrap_or_default())
+    }
 }
 
 #[derive(Default)]
 pub struct MediaDataBuilder {
     pub src: Option<MediaSrc>,
     pub mime: Option<Mime>,
+    pub codecs: Vec<Codec>,
     pub alt: Option<String>,
+    pub decorative: bool,
+    pub variants: Vec<ImageVariant>,
+    pub metadata: Option<MediaMetadata>,
+    pub accessibility: MediaAccessibility,
+    pub poster: Option<MediaData>,
 }
 
 impl MediaDataBuilder {
@@ -36,29 +112,129 @@ impl MediaDataBuilder {
         self
     }
 
+    pub fn asset(mut self, canister: Principal, path: &str) -> Self {
+        self.src = Some(MediaSrc::Asset {
+            canister,
+            path: path.to_string(),
+        });
+        self
+    }
+
+    pub fn data_uri(mut self, data_uri: &str) -> Self {
+        self.src = Some(MediaSrc::DataUri(data_uri.to_string()));
+        self
+    }
+
     pub fn mime(mut self, mime: Mime) -> Self {
         self.mime = Some(mime);
         self
     }
 
+    pub fn codecs(mut self, codecs: Vec<Codec>) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
     pub fn alt(mut self, alt: &str) -> Self {
         self.alt = Some(alt.to_string());
         self
     }
 
-    pub fn build(self) -> MediaData {
-        MediaData {
-            src: self.src.unwrap(),
-            mime: self.mime.unwrap(),
+    pub fn decorative(mut self) -> Self {
+        self.decorative = true;
+        self
+    }
+
+    pub fn variant(mut self, variant: ImageVariant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: MediaMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn track(mut self, track: TextTrack) -> Self {
+        self.accessibility.tracks.push(track);
+        self
+    }
+
+    pub fn transcript(mut self, transcript: Transcript) -> Self {
+        self.accessibility.transcripts.push(transcript);
+        self
+    }
+
+    pub fn poster(mut self, poster: MediaData) -> Self {
+        self.poster = Some(poster);
+        self
+    }
+
+    pub fn try_build(self) -> Result<MediaData, MediaDataError> {
+        let data = MediaData {
+            src: self
+                .src
+                .ok_or_else(|| MediaDataError::MissingField("src".to_string()))?,
+            mime: self
+                .mime
+                .ok_or_else(|| MediaDataError::MissingField("mime".to_string()))?,
+            codecs: Some(self.codecs).filter(|codecs| !codecs.is_empty()),
             alt: self.alt,
-        }
+            decorative: self.decorative.then_some(true),
+            variants: Some(self.variants).filter(|variants| !variants.is_empty()),
+            metadata: self.metadata,
+            accessibility: Some(self.accessibility)
+                .filter(|accessibility| accessibility != &MediaAccessibility::default()),
+            poster: self.poster.map(Box::new),
+        };
+        data.validate()?;
+        Ok(data)
+    }
+}
+
+impl Storable for MediaData {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
     }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MediaDataError {
+    #[error("media has no {0}")]
+    MissingField(String),
+    #[error("image has no alt text and is not marked decorative")]
+    MissingAlt,
+    #[error("alt text is longer than {} characters", MediaData::MAX_ALT_LENGTH)]
+    AltTooLong,
+    #[error(
+        "caption is longer than {} characters",
+        crate::item::MediaDataWithCaption::MAX_CAPTION_LENGTH
+    )]
+    CaptionTooLong,
+    #[error("poster is not an image")]
+    InvalidPoster,
+    #[error("media has invalid metadata: {0}")]
+    InvalidMetadata(MediaMetadataError),
 }
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub enum MediaSrc {
     URL(String),
+    /// CID, optionally followed by a path inside a directory CID, e.g. `<cid>/images/1.png`
     CID(String),
+    /// File served by an asset canister
+    Asset {
+        canister: Principal,
+        path: String,
+    },
+    /// `data:` URI of an image, video or audio
+    DataUri(String),
 }
 
 impl MediaSrc {
@@ -70,6 +246,392 @@ impl MediaSrc {
         match self {
             MediaSrc::URL(url) => url.clone(),
             MediaSrc::CID(cid) => cid.clone(),
+            MediaSrc::Asset { canister, path } => {
+                format!("{}/{}", canister, path.trim_start_matches('/'))
+            }
+            MediaSrc::DataUri(data_uri) => data_uri.clone(),
+        }
+    }
+
+    /// Resolve the source to a URL that frontends can load.
+    ///
+    /// CIDs resolve on the preferred IPFS gateway, and so do `ipfs://` URLs. Other URLs must be
+    /// http(s), so that `javascript:` and similar schemes never reach the page, and `data:` URIs
+    /// must hold an image, video or audio.
+    pub fn resolve(&self, config: &ResolverConfig) -> Result<Url, MediaSrcError> {
+        match self {
+            MediaSrc::URL(url) => {
+                let url = Url::parse(url.trim())
+                    .map_err(|error| MediaSrcError::InvalidUrl(error.to_string()))?;
+                match url.scheme() {
+                    "http" | "https" => Ok(url),
+                    "ipfs" => {
+                        let cid = url.host_str().unwrap_or_default();
+                        Self::resolve_cid(&format!("{}{}", cid, url.path()), config)
+                    }
+                    scheme => Err(MediaSrcError::UnsafeScheme(scheme.to_string())),
+                }
+            }
+            MediaSrc::CID(cid) => Self::resolve_cid(cid, config),
+            MediaSrc::Asset { canister, path } => {
+                let (scheme, host) = config
+                    .asset_base_url
+                    .split_once("://")
+                    .unwrap_or(("https", &config.asset_base_url));
+                let url = format!(
+                    "{}://{}.{}/{}",
+                    scheme,
+                    canister,
+                    host.trim_end_matches('/'),
+                    path.trim_start_matches('/')
+                );
+                Url::parse(&url).map_err(|error| MediaSrcError::InvalidUrl(error.to_string()))
+            }
+            MediaSrc::DataUri(data_uri) => {
+                let media_type = data_uri
+                    .strip_prefix("data:")
+                    .and_then(|rest| rest.split_once(','))
+                    .map(|(media_type, _)| media_type.to_ascii_lowercase())
+                    .ok_or(MediaSrcError::InvalidDataUri)?;
+                if !["image/", "video/", "audio/"]
+                    .iter()
+                    .any(|prefix| media_type.starts_with(prefix))
+                {
+                    return Err(MediaSrcError::InvalidDataUri);
+                }
+                Url::parse(data_uri).map_err(|_| MediaSrcError::InvalidDataUri)
+            }
+        }
+    }
+
+    fn resolve_cid(cid: &str, config: &ResolverConfig) -> Result<Url, MediaSrcError> {
+        let cid = cid.trim().trim_start_matches("/ipfs/");
+        let (cid, path) = cid.split_once('/').unwrap_or((cid, ""));
+        let cid = Cid::new(cid).map_err(MediaSrcError::InvalidCid)?;
+        let url = config
+            .ipfs
+            .url(&cid, path)
+            .ok_or(MediaSrcError::NoIpfsGateway)?;
+        Url::parse(&url).map_err(|error| MediaSrcError::InvalidUrl(error.to_string()))
+    }
+}
+
+/// Where [`MediaSrc::resolve`] points CIDs and asset canister files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolverConfig {
+    pub ipfs: IpfsGatewayConfig,
+    /// Scheme and domain under which asset canisters are served as subdomains,
+    /// e.g. `https://icp0.io`
+    pub asset_base_url: String,
+}
+
+impl ResolverConfig {
+    pub fn for_network(network: DfxNetwork) -> Self {
+        Self {
+            ipfs: IpfsGatewayConfig::for_network(network),
+            asset_base_url: match network {
+                DfxNetwork::Local => "http://localhost:4943".to_string(),
+                DfxNetwork::Ic => "https://icp0.io".to_string(),
+            },
+        }
+    }
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self::for_network(DfxNetwork::Ic)
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MediaSrcError {
+    #[error("media source is not a valid URL: {0}")]
+    InvalidUrl(String),
+    #[error("media source has an unsafe scheme ({0:?})")]
+    UnsafeScheme(String),
+    #[error("media source has an invalid CID: {0}")]
+    InvalidCid(CidError),
+    #[error("no IPFS gateway is configured")]
+    NoIpfsGateway,
+    #[error("media source is not a data URI of an image, video or audio")]
+    InvalidDataUri,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::mime::{MimeDocument, MimeImage, MimeModel, MimeVideo};
+
+    const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    fn resolve(src: MediaSrc) -> Result<String, MediaSrcError> {
+        src.resolve(&ResolverConfig::default())
+            .map(|url| url.to_string())
+    }
+
+    #[test]
+    fn test_try_build() {
+        assert_eq!(
+            MediaData::builder().cid(CID).try_build(),
+            Err(MediaDataError::MissingField("mime".to_string()))
+        );
+        assert_eq!(
+            MediaData::builder()
+                .mime(Mime::Image(MimeImage::Png))
+                .try_build(),
+            Err(MediaDataError::MissingField("src".to_string()))
+        );
+
+        let image = || {
+            MediaData::builder()
+                .cid(CID)
+                .mime(Mime::Image(MimeImage::Png))
+        };
+        assert_eq!(image().try_build(), Err(MediaDataError::MissingAlt));
+        assert_eq!(
+            image().alt(" ").try_build(),
+            Err(MediaDataError::MissingAlt)
+        );
+        assert!(image().decorative().try_build().is_ok());
+        assert_eq!(
+            image()
+                .alt(&"a".repeat(MediaData::MAX_ALT_LENGTH + 1))
+                .try_build(),
+            Err(MediaDataError::AltTooLong)
+        );
+        assert_eq!(
+            image()
+                .alt("Red mug")
+                .metadata(MediaMetadata {
+                    width: Some(0),
+                    ..Default::default()
+                })
+                .try_build(),
+            Err(MediaDataError::InvalidMetadata(
+                MediaMetadataError::InvalidDimensions
+            ))
+        );
+
+        let video = MediaData::builder()
+            .cid(CID)
+            .mime(Mime::Video(MimeVideo::Mp4))
+            .try_build()
+            .unwrap();
+        assert_eq!(video.alt, None);
+
+        let data = image().alt("Red mug").try_build().unwrap();
+        assert_eq!(MediaData::from_bytes(data.to_bytes()), data);
+    }
+
+    #[test]
+    fn test_try_build_model_and_document() {
+        let poster = MediaData::builder()
+            .cid(CID)
+            .mime(Mime::Image(MimeImage::Png))
+            .alt("Red chair")
+            .try_build()
+            .unwrap();
+        let model = || {
+            MediaData::builder()
+                .cid(CID)
+                .mime(Mime::Model(MimeModel::GltfBinary))
+        };
+        assert_eq!(model().try_build(), Err(MediaDataError::MissingAlt));
+        let data = model()
+            .alt("Red chair")
+            .poster(poster.clone())
+            .try_build()
+            .unwrap();
+        assert_eq!(data.poster.as_deref(), Some(&poster));
+        assert_eq!(
+            model().alt("Red chair").poster(data.clone()).try_build(),
+            Err(MediaDataError::InvalidPoster)
+        );
+
+        let manual = MediaData::builder()
+            .cid(CID)
+            .mime(Mime::Document(MimeDocument::Pdf))
+            .metadata(MediaMetadata {
+                page_count: Some(24),
+                ..Default::default()
+            })
+            .poster(poster)
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            manual.metadata.and_then(|metadata| metadata.page_count),
+            Some(24)
+        );
+        assert_eq!(
+            MediaData::builder()
+                .cid(CID)
+                .mime(Mime::Document(MimeDocument::Pdf))
+                .metadata(MediaMetadata {
+                    page_count: Some(0),
+                    ..Default::default()
+                })
+                .try_build(),
+            Err(MediaDataError::InvalidMetadata(
+                MediaMetadataError::InvalidPageCount
+            ))
+        );
+    }
+
+    #[test]
+    fn test_from_legacy_bytes() {
+        /// Shapes of [`MediaData`] and [`Mime`] before any of the new fields and subtypes
+        #[derive(CandidType)]
+        enum LegacyMimeVideo {
+            H264,
+        }
+        #[derive(CandidType)]
+        enum LegacyMime {
+            Video(LegacyMimeVideo),
         }
+        #[derive(CandidType)]
+        struct LegacyMediaData {
+            src: MediaSrc,
+            mime: LegacyMime,
+            alt: Option<String>,
+        }
+
+        let bytes = Encode!(&LegacyMediaData {
+            src: MediaSrc::CID(CID.to_string()),
+            mime: LegacyMime::Video(LegacyMimeVideo::H264),
+            alt: Some("Unboxing".to_string()),
+        })
+        .unwrap();
+        let data = MediaData::from_bytes(Cow::Owned(bytes));
+        assert_eq!(
+            data,
+            MediaData::builder()
+                .cid(CID)
+                .mime(Mime::Video(MimeVideo::Mp4))
+                .alt("Unboxing")
+                .try_build()
+                .unwrap()
+        );
+        assert!(!data.is_decorative());
+        assert_eq!(MediaData::from_bytes(data.to_bytes()), data);
+    }
+
+    #[test]
+    fn test_decode_without_metadata() {
+        /// Shape of [`MediaData`] before codecs, variants and metadata
+        #[derive(CandidType)]
+        struct LegacyMediaData {
+            src: MediaSrc,
+            mime: Mime,
+            alt: Option<String>,
+            decorative: bool,
+        }
+
+        let bytes = Encode!(&LegacyMediaData {
+            src: MediaSrc::CID(CID.to_string()),
+            mime: Mime::Image(MimeImage::Png),
+            alt: Some("Red mug".to_string()),
+            decorative: false,
+        })
+        .unwrap();
+        let data = Decode!(&bytes, MediaData).unwrap();
+        assert_eq!(data.metadata, None);
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        assert_eq!(
+            resolve(MediaSrc::URL("https://anthol.net/a.png".to_string())),
+            Ok("https://anthol.net/a.png".to_string())
+        );
+        assert_eq!(
+            resolve(MediaSrc::URL(format!("ipfs://{}/a.png", CID))),
+            Ok(format!("https://ipfs.anthol.net/ipfs/{}/a.png", CID))
+        );
+        assert_eq!(
+            resolve(MediaSrc::URL("javascript:alert(1)".to_string())),
+            Err(MediaSrcError::UnsafeScheme("javascript".to_string()))
+        );
+        assert_eq!(
+            resolve(MediaSrc::URL(" JavaScript:alert(1)".to_string())),
+            Err(MediaSrcError::UnsafeScheme("javascript".to_string()))
+        );
+        assert_eq!(
+            resolve(MediaSrc::URL("file:///etc/passwd".to_string())),
+            Err(MediaSrcError::UnsafeScheme("file".to_string()))
+        );
+        assert!(matches!(
+            resolve(MediaSrc::URL("/a.png".to_string())),
+            Err(MediaSrcError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_cid() {
+        assert_eq!(
+            resolve(MediaSrc::CID(CID.to_string())),
+            Ok(format!("https://ipfs.anthol.net/ipfs/{}", CID))
+        );
+        assert_eq!(
+            resolve(MediaSrc::CID(format!("/ipfs/{}/images/a b.png", CID))),
+            Ok(format!(
+                "https://ipfs.anthol.net/ipfs/{}/images/a%20b.png",
+                CID
+            ))
+        );
+        assert_eq!(
+            resolve(MediaSrc::CID("cid".to_string())),
+            Err(MediaSrcError::InvalidCid(CidError::UnsupportedMultibase(
+                "c".to_string()
+            )))
+        );
+
+        let config = ResolverConfig {
+            ipfs: IpfsGatewayConfig { gateways: vec![] },
+            ..Default::default()
+        };
+        assert_eq!(
+            MediaSrc::CID(CID.to_string()).resolve(&config),
+            Err(MediaSrcError::NoIpfsGateway)
+        );
+    }
+
+    #[test]
+    fn test_resolve_asset() {
+        let canister = Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, 2, 1, 1]);
+        let src = MediaSrc::Asset {
+            canister,
+            path: "/items/a.png".to_string(),
+        };
+        assert_eq!(
+            resolve(src.clone()),
+            Ok("https://ryjl3-tyaaa-aaaaa-aaaba-cai.icp0.io/items/a.png".to_string())
+        );
+        assert_eq!(
+            src.resolve(&ResolverConfig::for_network(DfxNetwork::Local))
+                .unwrap()
+                .to_string(),
+            "http://ryjl3-tyaaa-aaaaa-aaaba-cai.localhost:4943/items/a.png"
+        );
+    }
+
+    #[test]
+    fn test_resolve_data_uri() {
+        assert_eq!(
+            resolve(MediaSrc::DataUri(
+                "data:image/png;base64,iVBORw0KGgo=".to_string()
+            )),
+            Ok("data:image/png;base64,iVBORw0KGgo=".to_string())
+        );
+        assert_eq!(
+            resolve(MediaSrc::DataUri(
+                "data:text/html,<script>alert(1)</script>".to_string()
+            )),
+            Err(MediaSrcError::InvalidDataUri)
+        );
+        assert_eq!(
+            resolve(MediaSrc::DataUri("https://anthol.net/a.png".to_string())),
+            Err(MediaSrcError::InvalidDataUri)
+        );
     }
 }
@@ -0,0 +1,289 @@
+use super::{MediaData, MediaSrc};
+use crate::{media::mime::Mime, util::locale::LanguageTag};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Text alternatives of a video or audio
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub struct MediaAccessibility {
+    /// Timed text tracks of a video
+    pub tracks: Vec<TextTrack>,
+    /// Transcripts of an audio or video
+    pub transcripts: Vec<Transcript>,
+}
+
+/// WebVTT file for a `<track>` of a video
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct TextTrack {
+    pub src: MediaSrc,
+    pub kind: TextTrackKind,
+    pub language: LanguageTag,
+    /// Label shown in the track menu, e.g. `English (CC)`
+    pub label: Option<String>,
+}
+
+impl TextTrack {
+    /// Check whether the source looks like a WebVTT file, from its extension or `data:` type.
+    /// A CID without a path cannot be checked and is accepted.
+    pub fn is_webvtt(&self) -> bool {
+        let path = match &self.src {
+            MediaSrc::DataUri(data_uri) => {
+                return data_uri.get(..14).is_some_and(|prefix| {
+                    ["data:text/vtt;", "data:text/vtt,"]
+                        .iter()
+                        .any(|vtt| prefix.eq_ignore_ascii_case(vtt))
+                });
+            }
+            MediaSrc::CID(cid) => match cid.trim_start_matches("/ipfs/").split_once('/') {
+                Some((_, path)) => path,
+                None => return true,
+            },
+            MediaSrc::URL(url) => url.as_str(),
+            MediaSrc::Asset { path, .. } => path.as_str(),
+        };
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        path.rsplit_once('.')
+            .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case("vtt"))
+    }
+}
+
+/// Kind of a `<track>`
+#[derive(CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum TextTrackKind {
+    /// Translation of the dialogue
+    Subtitles,
+    /// Transcription of the dialogue and sound effects, for deaf and hard of hearing viewers
+    Captions,
+    /// Description of the visual content, for blind viewers
+    Descriptions,
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Transcript {
+    pub language: LanguageTag,
+    pub content: TranscriptContent,
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum TranscriptContent {
+    Text(String),
+    File(MediaSrc),
+}
+
+/// Accessibility problem of a media
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum AccessibilityIssue {
+    /// Image or 3D model with no alt text that is not marked decorative
+    MissingAlt,
+    /// Video with no captions or subtitles that is not marked decorative
+    MissingCaptions,
+    /// Audio with no transcript that is not marked decorative
+    MissingTranscript,
+    /// Track, by index, that is not a WebVTT file
+    TrackNotWebVtt(u32),
+    /// Text transcript that is empty
+    EmptyTranscript(u32),
+}
+
+impl MediaData {
+    /// List the accessibility problems of the media
+    pub fn accessibility_issues(&self) -> Vec<AccessibilityIssue> {
+        let mut issues = Vec::new();
+        let has_alt = self
+            .alt
+            .as_deref()
+            .is_some_and(|alt| !alt.trim().is_empty());
+        let default = MediaAccessibility::default();
+        let accessibility = self.accessibility.as_ref().unwrap_or(&default);
+
+        match &self.mime {
+            Mime::Image(_) | Mime::Model(_) if !has_alt && !self.is_decorative() => {
+                issues.push(AccessibilityIssue::MissingAlt);
+            }
+            Mime::Video(_)
+                if !self.is_decorative()
+                    && !accessibility.tracks.iter().any(|track| {
+                        matches!(
+                            track.kind,
+                            TextTrackKind::Captions | TextTrackKind::Subtitles
+                        )
+                    }) =>
+            {
+                issues.push(AccessibilityIssue::MissingCaptions);
+            }
+            Mime::Audio(_) if !self.is_decorative() && accessibility.transcripts.is_empty() => {
+                issues.push(AccessibilityIssue::MissingTranscript);
+            }
+            _ => {}
+        }
+
+        for (index, track) in accessibility.tracks.iter().enumerate() {
+            if !track.is_webvtt() {
+                issues.push(AccessibilityIssue::TrackNotWebVtt(index as u32));
+            }
+        }
+        for (index, transcript) in accessibility.transcripts.iter().enumerate() {
+            if matches!(&transcript.content, TranscriptContent::Text(text) if text.trim().is_empty())
+            {
+                issues.push(AccessibilityIssue::EmptyTranscript(index as u32));
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{
+        mime::{MimeAudio, MimeDocument, MimeImage, MimeModel, MimeVideo},
+        MediaDataBuilder,
+    };
+    use candid::{Decode, Encode};
+
+    fn track(src: MediaSrc, kind: TextTrackKind) -> TextTrack {
+        TextTrack {
+            src,
+            kind,
+            language: LanguageTag::new("en").unwrap(),
+            label: None,
+        }
+    }
+
+    fn media(mime: Mime) -> MediaDataBuilder {
+        MediaData::builder().url("https://anthol.net/a").mime(mime)
+    }
+
+    #[test]
+    fn test_is_webvtt() {
+        for src in [
+            MediaSrc::URL("https://anthol.net/en.VTT?v=2".to_string()),
+            MediaSrc::CID("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_string()),
+            MediaSrc::CID("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG/en.vtt".to_string()),
+            MediaSrc::DataUri("data:text/vtt,WEBVTT".to_string()),
+        ] {
+            assert!(
+                track(src.clone(), TextTrackKind::Captions).is_webvtt(),
+                "{:?}",
+                src
+            );
+        }
+        for src in [
+            MediaSrc::URL("https://anthol.net/en.srt".to_string()),
+            MediaSrc::URL("https://anthol.net/vtt".to_string()),
+            MediaSrc::DataUri("data:text/plain,WEBVTT".to_string()),
+        ] {
+            assert!(
+                !track(src.clone(), TextTrackKind::Captions).is_webvtt(),
+                "{:?}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_accessibility_issues() {
+        let image = media(Mime::Image(MimeImage::Png)).alt("Red mug");
+        assert_eq!(image.try_build().unwrap().accessibility_issues(), vec![]);
+        // Alt text is required to build, so it is removed afterwards as stored data may lack it
+        let mut image = media(Mime::Image(MimeImage::Png))
+            .alt("Red mug")
+            .try_build()
+            .unwrap();
+        image.alt = None;
+        assert_eq!(
+            image.accessibility_issues(),
+            vec![AccessibilityIssue::MissingAlt]
+        );
+        let image = media(Mime::Image(MimeImage::Png))
+            .decorative()
+            .try_build()
+            .unwrap();
+        assert_eq!(image.accessibility_issues(), vec![]);
+        let mut model = media(Mime::Model(MimeModel::Usdz))
+            .alt("Red chair")
+            .try_build()
+            .unwrap();
+        model.alt = None;
+        assert_eq!(
+            model.accessibility_issues(),
+            vec![AccessibilityIssue::MissingAlt]
+        );
+        let document = media(Mime::Document(MimeDocument::Pdf))
+            .try_build()
+            .unwrap();
+        assert_eq!(document.accessibility_issues(), vec![]);
+
+        let video = media(Mime::Video(MimeVideo::Mp4))
+            .track(track(
+                MediaSrc::URL("https://anthol.net/en.srt".to_string()),
+                TextTrackKind::Descriptions,
+            ))
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            video.accessibility_issues(),
+            vec![
+                AccessibilityIssue::MissingCaptions,
+                AccessibilityIssue::TrackNotWebVtt(0)
+            ]
+        );
+        let video = media(Mime::Video(MimeVideo::Mp4))
+            .track(track(
+                MediaSrc::URL("https://anthol.net/en.vtt".to_string()),
+                TextTrackKind::Captions,
+            ))
+            .try_build()
+            .unwrap();
+        assert_eq!(video.accessibility_issues(), vec![]);
+
+        let audio = media(Mime::Audio(MimeAudio::Mp3)).try_build().unwrap();
+        assert_eq!(audio.accessibility, None);
+        assert_eq!(
+            audio.accessibility_issues(),
+            vec![AccessibilityIssue::MissingTranscript]
+        );
+        let audio = media(Mime::Audio(MimeAudio::Mp3))
+            .decorative()
+            .try_build()
+            .unwrap();
+        assert_eq!(audio.accessibility_issues(), vec![]);
+        let audio = media(Mime::Audio(MimeAudio::Mp3))
+            .transcript(Transcript {
+                language: LanguageTag::new("en").unwrap(),
+                content: TranscriptContent::Text(" ".to_string()),
+            })
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            audio.accessibility_issues(),
+            vec![AccessibilityIssue::EmptyTranscript(0)]
+        );
+    }
+
+    #[test]
+    fn test_decode_without_accessibility() {
+        /// Shape of [`MediaData`] before codecs, variants, metadata and accessibility
+        #[derive(CandidType)]
+        struct LegacyMediaData {
+            src: MediaSrc,
+            mime: Mime,
+            alt: Option<String>,
+            decorative: bool,
+        }
+
+        let bytes = Encode!(&LegacyMediaData {
+            src: MediaSrc::URL("https://anthol.net/a.mp3".to_string()),
+            mime: Mime::Audio(MimeAudio::Mp3),
+            alt: None,
+            decorative: false,
+        })
+        .unwrap();
+        let audio = Decode!(&bytes, MediaData).unwrap();
+        assert_eq!(audio.accessibility, None);
+        assert_eq!(
+            audio.accessibility_issues(),
+            vec![AccessibilityIssue::MissingTranscript]
+        );
+    }
+}
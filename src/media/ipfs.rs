@@ -0,0 +1,408 @@
+use crate::{consts::IPFS_GATEWAY, util::network::DfxNetwork};
+use candid::CandidType;
+use data_encoding::{BASE32_NOPAD, HEXLOWER_PERMISSIVE};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Multicodec of UnixFS files and directories, the only codec of CIDv0
+pub const DAG_PB: u64 = 0x70;
+/// Multicodec of raw blocks
+pub const RAW: u64 = 0x55;
+/// Multihash code of SHA-256, the only hash of CIDv0
+pub const SHA2_256: u64 = 0x12;
+
+const SHA2_256_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CidVersion {
+    V0,
+    V1,
+}
+
+/// IPFS content identifier.
+///
+/// CIDv0 is parsed from base58btc `Qm...` strings and CIDv1 from multibase strings in base32,
+/// base58btc or base16. CIDv0 is displayed as base58btc and CIDv1 as lowercase base32, which is
+/// safe for subdomains.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    version: CidVersion,
+    codec: u64,
+    hash_code: u64,
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// The length of a CIDv0 string
+    pub const V0_LENGTH: usize = 46;
+
+    pub fn new<T: AsRef<str>>(cid: T) -> Result<Self, CidError> {
+        Self::from_str_core(cid.as_ref())
+    }
+
+    pub fn version(&self) -> CidVersion {
+        self.version
+    }
+
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// Get the multihash code, e.g. [`SHA2_256`]
+    pub fn hash_code(&self) -> u64 {
+        self.hash_code
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Convert to CIDv1, which every CID can be
+    pub fn to_v1(&self) -> Self {
+        Self {
+            version: CidVersion::V1,
+            ..self.clone()
+        }
+    }
+
+    /// Convert to CIDv0, which only works for SHA-256 DAG-PB CIDs
+    pub fn to_v0(&self) -> Result<Self, CidError> {
+        if self.codec != DAG_PB || self.hash_code != SHA2_256 {
+            return Err(CidError::NotV0Compatible);
+        }
+        Ok(Self {
+            version: CidVersion::V0,
+            ..self.clone()
+        })
+    }
+
+    fn multihash(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.digest.len() + 4);
+        write_varint(&mut bytes, self.hash_code);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    fn from_str_core(s: &str) -> Result<Self, CidError> {
+        let s = s.trim();
+        if s.len() == Self::V0_LENGTH && s.starts_with("Qm") {
+            let bytes = bs58::decode(s)
+                .into_vec()
+                .map_err(|_| CidError::InvalidEncoding)?;
+            let (hash_code, digest) = read_multihash(&bytes)?;
+            if hash_code != SHA2_256 || digest.len() != SHA2_256_LENGTH {
+                return Err(CidError::InvalidMultihash);
+            }
+            return Ok(Self {
+                version: CidVersion::V0,
+                codec: DAG_PB,
+                hash_code,
+                digest: digest.to_vec(),
+            });
+        }
+
+        let mut chars = s.chars();
+        let base = chars.next().ok_or(CidError::Empty)?;
+        let rest = chars.as_str();
+        let bytes = match base {
+            'b' | 'B' => BASE32_NOPAD
+                .decode(rest.to_ascii_uppercase().as_bytes())
+                .ok(),
+            'f' | 'F' => HEXLOWER_PERMISSIVE.decode(rest.as_bytes()).ok(),
+            'z' => bs58::decode(rest).into_vec().ok(),
+            _ => return Err(CidError::UnsupportedMultibase(base.to_string())),
+        }
+        .ok_or(CidError::InvalidEncoding)?;
+
+        let mut rest = bytes.as_slice();
+        let version = read_varint(&mut rest)?;
+        if version != 1 {
+            return Err(CidError::UnsupportedVersion(version));
+        }
+        let codec = read_varint(&mut rest)?;
+        let (hash_code, digest) = read_multihash(rest)?;
+        Ok(Self {
+            version: CidVersion::V1,
+            codec,
+            hash_code,
+            digest: digest.to_vec(),
+        })
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            CidVersion::V0 => f.write_str(&bs58::encode(self.multihash()).into_string()),
+            CidVersion::V1 => {
+                let mut bytes = Vec::new();
+                write_varint(&mut bytes, 1);
+                write_varint(&mut bytes, self.codec);
+                bytes.extend(self.multihash());
+                write!(f, "b{}", BASE32_NOPAD.encode(&bytes).to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+impl FromStr for Cid {
+    type Err = CidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
+    }
+}
+
+impl TryFrom<&str> for Cid {
+    type Error = CidError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
+    }
+}
+
+/// Read an unsigned LEB128 varint as used by multiformats, advancing the slice
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, CidError> {
+    let mut value = 0u64;
+    for (index, &byte) in bytes.iter().enumerate().take(9) {
+        value |= ((byte & 0x7F) as u64) << (index * 7);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[index + 1..];
+            return Ok(value);
+        }
+    }
+    Err(CidError::InvalidVarint)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_multihash(mut bytes: &[u8]) -> Result<(u64, &[u8]), CidError> {
+    let hash_code = read_varint(&mut bytes)?;
+    let length = read_varint(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(CidError::InvalidMultihash);
+    }
+    Ok((hash_code, bytes))
+}
+
+/// How a gateway addresses content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatewayStyle {
+    /// `https://gateway.example/ipfs/<cid>/<path>`
+    Path,
+    /// `https://<cidv1>.ipfs.gateway.example/<path>`, which isolates the origin of each CID
+    Subdomain,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IpfsGateway {
+    /// Scheme and host of the gateway without a trailing slash, e.g. `https://dweb.link`
+    pub base_url: String,
+    pub style: GatewayStyle,
+}
+
+impl IpfsGateway {
+    pub fn path(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            style: GatewayStyle::Path,
+        }
+    }
+
+    pub fn subdomain(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            style: GatewayStyle::Subdomain,
+        }
+    }
+
+    /// Get the URL of the content, or of `path` inside a directory CID
+    pub fn url(&self, cid: &Cid, path: &str) -> String {
+        let path = path.trim_start_matches('/');
+        let separator = if path.is_empty() { "" } else { "/" };
+        match self.style {
+            GatewayStyle::Path => format!("{}/ipfs/{}{}{}", self.base_url, cid, separator, path),
+            GatewayStyle::Subdomain => {
+                let (scheme, host) = self
+                    .base_url
+                    .split_once("://")
+                    .unwrap_or(("https", &self.base_url));
+                format!("{}://{}.ipfs.{}/{}", scheme, cid.to_v1(), host, path)
+            }
+        }
+    }
+}
+
+/// IPFS gateways in order of preference, so that clients can fall back to the next one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpfsGatewayConfig {
+    pub gateways: Vec<IpfsGateway>,
+}
+
+impl IpfsGatewayConfig {
+    /// Get the default gateways of the network.
+    ///
+    /// Local networks prefer a local IPFS node and fall back to the public gateways.
+    pub fn for_network(network: DfxNetwork) -> Self {
+        let public = [
+            IpfsGateway::path(IPFS_GATEWAY.trim_end_matches("/ipfs/")),
+            IpfsGateway::subdomain("https://dweb.link"),
+            IpfsGateway::path("https://ipfs.io"),
+        ];
+        let gateways = match network {
+            DfxNetwork::Local => std::iter::once(IpfsGateway::path("http://127.0.0.1:8080"))
+                .chain(public)
+                .collect(),
+            DfxNetwork::Ic => public.to_vec(),
+        };
+        Self { gateways }
+    }
+
+    /// Get the URL on the preferred gateway
+    pub fn url(&self, cid: &Cid, path: &str) -> Option<String> {
+        self.gateways.first().map(|gateway| gateway.url(cid, path))
+    }
+
+    /// Get the URLs on every gateway, preferred first
+    pub fn urls(&self, cid: &Cid, path: &str) -> Vec<String> {
+        self.gateways
+            .iter()
+            .map(|gateway| gateway.url(cid, path))
+            .collect()
+    }
+}
+
+impl Default for IpfsGatewayConfig {
+    fn default() -> Self {
+        Self::for_network(DfxNetwork::Ic)
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CidError {
+    #[error("CID is empty")]
+    Empty,
+    #[error("CID has an unsupported multibase prefix ({0:?})")]
+    UnsupportedMultibase(String),
+    #[error("CID is not validly encoded")]
+    InvalidEncoding,
+    #[error("CID has an invalid varint")]
+    InvalidVarint,
+    #[error("CID has an unsupported version ({0})")]
+    UnsupportedVersion(u64),
+    #[error("CID has an invalid multihash")]
+    InvalidMultihash,
+    #[error("CID cannot be converted to CIDv0")]
+    NotV0Compatible,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+    const V1: &str = "bafybeie5nqv6kd3qnfjupgvz34woh3oksc3iau6abmyajn7qvtf6d2ho34";
+
+    #[test]
+    fn test_cid_parse() {
+        let v0 = Cid::new(V0).unwrap();
+        assert_eq!(v0.version(), CidVersion::V0);
+        assert_eq!(v0.codec(), DAG_PB);
+        assert_eq!(v0.hash_code(), SHA2_256);
+        assert_eq!(v0.digest().len(), 32);
+        assert_eq!(v0.to_string(), V0);
+
+        let v1 = Cid::new(V1).unwrap();
+        assert_eq!(v1.version(), CidVersion::V1);
+        assert_eq!(v1.to_string(), V1);
+        assert_eq!(Cid::new(V1.to_ascii_uppercase()).unwrap(), v1);
+        assert_eq!(
+            Cid::new("f017012209d6c2be50f706953479ab9df2ce3edca90b68053c00b3004b7f0accbe1e8eedf")
+                .unwrap(),
+            v1
+        );
+        let base58 = format!(
+            "z{}",
+            bs58::encode(
+                BASE32_NOPAD
+                    .decode(V1[1..].to_ascii_uppercase().as_bytes())
+                    .unwrap()
+            )
+            .into_string()
+        );
+        assert_eq!(Cid::new(base58).unwrap(), v1);
+    }
+
+    #[test]
+    fn test_cid_convert() {
+        let v0 = Cid::new(V0).unwrap();
+        let v1 = Cid::new(V1).unwrap();
+        assert_eq!(v0.to_v1(), v1);
+        assert_eq!(v1.to_v0().unwrap(), v0);
+
+        let raw = Cid::new("bafkreie5nqv6kd3qnfjupgvz34woh3oksc3iau6abmyajn7qvtf6d2ho34").unwrap();
+        assert_eq!(raw.codec(), RAW);
+        assert_eq!(raw.digest(), v0.digest());
+        assert_eq!(raw.to_v0(), Err(CidError::NotV0Compatible));
+    }
+
+    #[test]
+    fn test_cid_invalid() {
+        assert_eq!(Cid::new(""), Err(CidError::Empty));
+        assert_eq!(
+            Cid::new("mAXASIA"),
+            Err(CidError::UnsupportedMultibase("m".to_string()))
+        );
+        assert_eq!(
+            Cid::new("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0"),
+            Err(CidError::InvalidEncoding)
+        );
+        assert_eq!(Cid::new("b!!!"), Err(CidError::InvalidEncoding));
+        assert_eq!(
+            Cid::new(&V1[..V1.len() - 2]),
+            Err(CidError::InvalidMultihash)
+        );
+        // A CIDv0 multihash wrapped in multibase is not a CIDv1
+        assert_eq!(
+            Cid::new("f12209d6c2be50f706953479ab9df2ce3edca90b68053c00b3004b7f0accbe1e8eedf"),
+            Err(CidError::UnsupportedVersion(0x12))
+        );
+        assert_eq!(Cid::new("ffff"), Err(CidError::InvalidEncoding));
+        assert_eq!(Cid::new("fffff"), Err(CidError::InvalidVarint));
+    }
+
+    #[test]
+    fn test_gateway_url() {
+        let cid = Cid::new(V0).unwrap();
+        assert_eq!(
+            IpfsGateway::path("https://ipfs.io/").url(&cid, ""),
+            format!("https://ipfs.io/ipfs/{}", V0)
+        );
+        assert_eq!(
+            IpfsGateway::path("https://ipfs.io").url(&cid, "/images/1.png"),
+            format!("https://ipfs.io/ipfs/{}/images/1.png", V0)
+        );
+        assert_eq!(
+            IpfsGateway::subdomain("https://dweb.link").url(&cid, "images/1.png"),
+            format!("https://{}.ipfs.dweb.link/images/1.png", V1)
+        );
+
+        let config = IpfsGatewayConfig::for_network(DfxNetwork::Local);
+        assert_eq!(
+            config.url(&cid, ""),
+            Some(format!("http://127.0.0.1:8080/ipfs/{}", V0))
+        );
+        assert_eq!(config.urls(&cid, "").len(), 4);
+        assert_eq!(
+            IpfsGatewayConfig::default().url(&cid, ""),
+            Some(format!("https://ipfs.anthol.net/ipfs/{}", V0))
+        );
+    }
+}
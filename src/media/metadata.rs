@@ -0,0 +1,203 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Intrinsic properties of a media file, so that pages can reserve layout space and show a
+/// placeholder before it loads, and check its integrity after
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub struct MediaMetadata {
+    /// Intrinsic width in pixels of an image or video
+    pub width: Option<u32>,
+    /// Intrinsic height in pixels of an image or video
+    pub height: Option<u32>,
+    /// Duration in milliseconds of a video or audio
+    pub duration_ms: Option<u64>,
+    /// Number of pages of a document
+    pub page_count: Option<u32>,
+    pub placeholder: Option<Placeholder>,
+    pub byte_size: Option<u64>,
+    pub sha256: Option<[u8; 32]>,
+}
+
+impl MediaMetadata {
+    /// Create metadata with the size and hash of the file
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        Self {
+            byte_size: Some(bytes.len() as u64),
+            sha256: Some(Sha256::digest(bytes).into()),
+            ..Default::default()
+        }
+    }
+
+    /// Get the aspect ratio as the smallest width and height, e.g. `(16, 9)` for CSS
+    /// `aspect-ratio: 16 / 9`
+    pub fn aspect_ratio(&self) -> Option<(u32, u32)> {
+        let (width, height) = (self.width?, self.height?);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (width, height);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        Some((width / a, height / a))
+    }
+
+    /// Get the Subresource Integrity value for the `integrity` attribute, e.g. `sha256-...`
+    pub fn integrity(&self) -> Option<String> {
+        self.sha256
+            .map(|hash| format!("sha256-{}", STANDARD.encode(hash)))
+    }
+
+    /// Check that the file has the recorded size and hash
+    pub fn verify(&self, bytes: &[u8]) -> Result<(), MediaMetadataError> {
+        if let Some(byte_size) = self.byte_size {
+            if byte_size != bytes.len() as u64 {
+                return Err(MediaMetadataError::SizeMismatch {
+                    expected: byte_size,
+                    actual: bytes.len() as u64,
+                });
+            }
+        }
+        if let Some(sha256) = self.sha256 {
+            if sha256 != <[u8; 32]>::from(Sha256::digest(bytes)) {
+                return Err(MediaMetadataError::HashMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), MediaMetadataError> {
+        if self.width == Some(0) || self.height == Some(0) {
+            return Err(MediaMetadataError::InvalidDimensions);
+        }
+        if self.page_count == Some(0) {
+            return Err(MediaMetadataError::InvalidPageCount);
+        }
+        match &self.placeholder {
+            Some(placeholder) => placeholder.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Tiny blurred preview shown while a media file loads
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Placeholder {
+    /// [BlurHash](https://blurha.sh) string
+    BlurHash(String),
+    /// [ThumbHash](https://evanw.github.io/thumbhash/) bytes
+    ThumbHash(Vec<u8>),
+}
+
+impl Placeholder {
+    /// The maximum size of a ThumbHash in bytes
+    pub const MAX_THUMB_HASH_SIZE: usize = 64;
+
+    /// Check that a BlurHash has the length given by its number of components, and that a
+    /// ThumbHash has at least its header
+    pub fn validate(&self) -> Result<(), MediaMetadataError> {
+        let is_valid = match self {
+            Placeholder::BlurHash(hash) => {
+                const BASE83: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+                hash.chars().all(|c| BASE83.contains(c))
+                    && hash.chars().next().is_some_and(|size_flag| {
+                        let size_flag = BASE83.find(size_flag).unwrap();
+                        let components = (size_flag / 9 + 1) * (size_flag % 9 + 1);
+                        hash.len() == 4 + 2 * components
+                    })
+            }
+            Placeholder::ThumbHash(hash) => (5..=Self::MAX_THUMB_HASH_SIZE).contains(&hash.len()),
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(MediaMetadataError::InvalidPlaceholder)
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MediaMetadataError {
+    #[error("Media has a zero width or height")]
+    InvalidDimensions,
+    #[error("Document has no pages")]
+    InvalidPageCount,
+    #[error("Media has an invalid placeholder")]
+    InvalidPlaceholder,
+    #[error("Media is {actual} bytes instead of {expected}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("Media does not match its SHA-256 hash")]
+    HashMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_of_bytes() {
+        let metadata = MediaMetadata::of_bytes(b"abc");
+        assert_eq!(metadata.byte_size, Some(3));
+        assert_eq!(
+            metadata.integrity().unwrap(),
+            "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="
+        );
+        assert_eq!(metadata.verify(b"abc"), Ok(()));
+        assert_eq!(
+            metadata.verify(b"abcd"),
+            Err(MediaMetadataError::SizeMismatch {
+                expected: 3,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            metadata.verify(b"abd"),
+            Err(MediaMetadataError::HashMismatch)
+        );
+        assert_eq!(MediaMetadata::default().verify(b"abd"), Ok(()));
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        let metadata = |width, height| MediaMetadata {
+            width,
+            height,
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata(Some(1920), Some(1080)).aspect_ratio(),
+            Some((16, 9))
+        );
+        assert_eq!(metadata(Some(640), Some(640)).aspect_ratio(), Some((1, 1)));
+        assert_eq!(metadata(Some(640), None).aspect_ratio(), None);
+        assert_eq!(
+            metadata(Some(640), Some(0)).validate(),
+            Err(MediaMetadataError::InvalidDimensions)
+        );
+    }
+
+    #[test]
+    fn test_validate_placeholder() {
+        assert_eq!(
+            Placeholder::BlurHash("LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_string()).validate(),
+            Ok(())
+        );
+        for hash in [
+            "",
+            "LEHV6nWB2yk8pyo0adR*.7kCMdn",
+            "LEHV6nWB2yk8pyo0adR*.7kCMdn\"",
+        ] {
+            assert_eq!(
+                Placeholder::BlurHash(hash.to_string()).validate(),
+                Err(MediaMetadataError::InvalidPlaceholder)
+            );
+        }
+        assert_eq!(Placeholder::ThumbHash(vec![0; 24]).validate(), Ok(()));
+        assert_eq!(
+            Placeholder::ThumbHash(vec![0; 4]).validate(),
+            Err(MediaMetadataError::InvalidPlaceholder)
+        );
+    }
+}
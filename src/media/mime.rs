@@ -1,21 +1,32 @@
 use candid::CandidType;
-use serde::{Deserialize, Serialize};
-use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{fmt, str::FromStr};
 
 mod audio;
+mod codec;
+mod detect;
+mod document;
 mod image;
+mod model;
 mod video;
 
 pub use audio::MimeAudio;
+pub use codec::{CanPlay, Codec, CodecKind, PlaybackSupport};
+pub use detect::MimeDetectionError;
+pub use document::MimeDocument;
 pub use image::MimeImage;
+pub use model::MimeModel;
 pub use video::MimeVideo;
 
-#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[derive(CandidType, Clone, Debug, Serialize, Hash, Eq, PartialEq)]
 pub enum Mime {
     Other(String),
     Image(MimeImage),
     Video(MimeVideo),
     Audio(MimeAudio),
+    Model(MimeModel),
+    /// `application/*` type of a document. Other `application/*` types are [`Mime::Other`].
+    Document(MimeDocument),
 }
 
 impl fmt::Display for Mime {
@@ -25,6 +36,402 @@ impl fmt::Display for Mime {
             Mime::Image(subtype) => write!(f, "image/{}", subtype),
             Mime::Video(subtype) => write!(f, "video/{}", subtype),
             Mime::Audio(subtype) => write!(f, "audio/{}", subtype),
+            Mime::Model(subtype) => write!(f, "model/{}", subtype),
+            Mime::Document(subtype) => write!(f, "application/{}", subtype),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Mime {
+    /// Read other types that now have a category as that category, e.g. `application/pdf`
+    /// stored as [`Mime::Other`] before documents existed
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        enum Repr {
+            Other(String),
+            Image(MimeImage),
+            Video(MimeVideo),
+            Audio(MimeAudio),
+            Model(MimeModel),
+            Document(MimeDocument),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Other(other) => match other.parse() {
+                Ok(Mime::Other(_)) | Err(_) => Mime::Other(other),
+                Ok(mime) => mime,
+            },
+            Repr::Image(subtype) => Mime::Image(subtype),
+            Repr::Video(subtype) => Mime::Video(subtype),
+            Repr::Audio(subtype) => Mime::Audio(subtype),
+            Repr::Model(subtype) => Mime::Model(subtype),
+            Repr::Document(subtype) => Mime::Document(subtype),
+        })
+    }
+}
+
+impl FromStr for Mime {
+    type Err = MimeError;
+
+    /// Parse a MIME type such as `image/svg+xml`, ignoring case and any parameters.
+    /// Use [`ContentType`] to keep the parameters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse::<ContentType>()?.mime)
+    }
+}
+
+impl Mime {
+    fn from_essence(type_: &str, subtype: &str) -> Result<Self, MimeError> {
+        let type_ = parse_token(type_)?;
+        let subtype = parse_token(subtype)?;
+        Ok(match type_.as_str() {
+            "image" => Mime::Image(subtype.parse()?),
+            "video" => Mime::Video(subtype.parse()?),
+            "audio" => Mime::Audio(subtype.parse()?),
+            "model" => Mime::Model(subtype.parse()?),
+            "application" => match subtype.parse() {
+                Ok(document) => Mime::Document(document),
+                Err(_) => Mime::Other(format!("{}/{}", type_, subtype)),
+            },
+            _ => Mime::Other(format!("{}/{}", type_, subtype)),
+        })
+    }
+}
+
+impl From<&::mime::Mime> for Mime {
+    fn from(mime: &::mime::Mime) -> Self {
+        let (type_, subtype) = mime.essence_str().split_once('/').unwrap_or_default();
+        // The mime crate only holds valid tokens
+        Mime::from_essence(type_, subtype)
+            .unwrap_or_else(|_| Mime::Other(mime.essence_str().to_ascii_lowercase()))
+    }
+}
+
+impl TryFrom<&Mime> for ::mime::Mime {
+    type Error = MimeError;
+
+    fn try_from(mime: &Mime) -> Result<Self, Self::Error> {
+        ContentType::from(mime.clone()).try_into()
+    }
+}
+
+/// MIME type with its parameters, as in a `Content-Type` header such as
+/// `text/plain; charset=utf-8`
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct ContentType {
+    pub mime: Mime,
+    /// Parameters in their original order, with lowercase names
+    pub params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// Get the value of a parameter, ignoring the case of its name
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl From<Mime> for ContentType {
+    fn from(mime: Mime) -> Self {
+        Self {
+            mime,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mime)?;
+        for (name, value) in &self.params {
+            if !value.is_empty() && value.chars().all(is_token_char) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "; {}=\"{}\"", name, escaped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = MimeError;
+
+    /// Parse a `Content-Type` header value. Type, subtype and parameter names are
+    /// case-insensitive, and parameter values may be quoted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(MimeError::Empty);
+        }
+        let (essence, mut rest) = s.split_once(';').unwrap_or((s, ""));
+        let (type_, subtype) = essence
+            .trim()
+            .split_once('/')
+            .ok_or(MimeError::MissingSubtype)?;
+        let mime = Mime::from_essence(type_, subtype)?;
+
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ';']);
+            if rest.is_empty() {
+                break;
+            }
+            let (name, after) = rest
+                .split_once('=')
+                .ok_or_else(|| MimeError::InvalidParameter(rest.to_string()))?;
+            let name = parse_token(name.trim())
+                .map_err(|_| MimeError::InvalidParameter(name.trim().to_string()))?;
+            let (value, after) = parse_param_value(after.trim_start())
+                .ok_or_else(|| MimeError::InvalidParameter(name.clone()))?;
+            params.push((name, value));
+            rest = after;
+        }
+        Ok(Self { mime, params })
+    }
+}
+
+impl From<&::mime::Mime> for ContentType {
+    fn from(mime: &::mime::Mime) -> Self {
+        Self {
+            mime: mime.into(),
+            params: mime
+                .params()
+                .map(|(name, value)| (name.as_str().to_string(), value.as_str().to_string()))
+                .collect(),
         }
     }
 }
+
+impl TryFrom<ContentType> for ::mime::Mime {
+    type Error = MimeError;
+
+    /// Fail for an [`Mime::Other`] or subtype `Other` that is not a valid MIME type
+    fn try_from(content_type: ContentType) -> Result<Self, Self::Error> {
+        let s = content_type.to_string();
+        s.parse().map_err(|_| MimeError::Invalid(s))
+    }
+}
+
+/// Get the major and compatible brands of an ISO base media file (MP4, HEIF, ...)
+fn ftyp_brands(bytes: &[u8]) -> Vec<&[u8; 4]> {
+    if bytes.get(4..8) != Some(b"ftyp") {
+        return Vec::new();
+    }
+    let size = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+    let end = size.clamp(8, bytes.len());
+    let major = bytes
+        .get(8..12.min(end))
+        .and_then(|brand| brand.try_into().ok());
+    let compatible = bytes
+        .get(16..end)
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .map(|brand| brand.try_into().unwrap());
+    major.into_iter().chain(compatible).collect()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Parse a token of a MIME type or parameter name, lowercased
+fn parse_token(s: &str) -> Result<String, MimeError> {
+    if s.is_empty() {
+        return Err(MimeError::Empty);
+    }
+    if !s.chars().all(is_token_char) {
+        return Err(MimeError::InvalidToken(s.to_string()));
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
+/// Check whether the character may appear in a token (RFC 9110)
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Parse a token or quoted string, returning the value and the rest after it
+fn parse_param_value(s: &str) -> Option<(String, &str)> {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s.find(';').unwrap_or(s.len());
+        let value = s[..end].trim_end();
+        return (!value.is_empty() && value.chars().all(is_token_char))
+            .then(|| (value.to_string(), &s[end..]));
+    };
+
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let rest = &quoted[i + 1..];
+                let rest = rest.trim_start();
+                return (rest.is_empty() || rest.starts_with(';')).then_some((value, rest));
+            }
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MimeError {
+    #[error("MIME type is empty")]
+    Empty,
+    #[error("MIME type has no subtype")]
+    MissingSubtype,
+    #[error("MIME type has an invalid token: {0:?}")]
+    InvalidToken(String),
+    #[error("MIME type has an invalid parameter: {0:?}")]
+    InvalidParameter(String),
+    #[error("MIME type is invalid: {0:?}")]
+    Invalid(String),
+    #[error("MIME type has an invalid codec: {0:?}")]
+    InvalidCodec(String),
+    #[error("MIME type is not a known document type: {0:?}")]
+    UnknownDocument(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Decode, Encode};
+
+    #[test]
+    fn test_parse_mime() {
+        assert_eq!("image/png".parse(), Ok(Mime::Image(MimeImage::Png)));
+        assert_eq!("IMAGE/JPEG".parse(), Ok(Mime::Image(MimeImage::Jpeg)));
+        assert_eq!("image/jpg".parse(), Ok(Mime::Image(MimeImage::Jpeg)));
+        assert_eq!("image/svg+xml".parse(), Ok(Mime::Image(MimeImage::Svg)));
+        assert_eq!(
+            "image/x-icon".parse(),
+            Ok(Mime::Image(MimeImage::Other("x-icon".to_string())))
+        );
+        assert_eq!("audio/mpeg".parse(), Ok(Mime::Audio(MimeAudio::Mp3)));
+        assert_eq!(
+            " video/webm; codecs=vp9".parse(),
+            Ok(Mime::Video(MimeVideo::Webm))
+        );
+        assert_eq!(
+            "Application/PDF".parse(),
+            Ok(Mime::Document(MimeDocument::Pdf))
+        );
+        assert_eq!(
+            "application/zip".parse(),
+            Ok(Mime::Other("application/zip".to_string()))
+        );
+        assert_eq!(
+            "model/gltf-binary".parse(),
+            Ok(Mime::Model(MimeModel::GltfBinary))
+        );
+        assert_eq!(
+            "model/vnd.pixar.usdz".parse(),
+            Ok(Mime::Model(MimeModel::Usdz))
+        );
+
+        assert_eq!("".parse::<Mime>(), Err(MimeError::Empty));
+        assert_eq!("image".parse::<Mime>(), Err(MimeError::MissingSubtype));
+        assert_eq!("image/".parse::<Mime>(), Err(MimeError::Empty));
+        assert_eq!(
+            "image/p ng".parse::<Mime>(),
+            Err(MimeError::InvalidToken("p ng".to_string()))
+        );
+
+        for mime in [
+            Mime::Image(MimeImage::Svg),
+            Mime::Image(MimeImage::Webp),
+            Mime::Video(MimeVideo::Quicktime),
+            Mime::Audio(MimeAudio::Flac),
+            Mime::Model(MimeModel::GltfJson),
+            Mime::Model(MimeModel::Usdz),
+            Mime::Document(MimeDocument::Pdf),
+            Mime::Other("application/octet-stream".to_string()),
+        ] {
+            assert_eq!(mime.to_string().parse(), Ok(mime));
+        }
+    }
+
+    #[test]
+    fn test_normalize_other_on_read() {
+        let stored = Mime::Other("application/pdf".to_string());
+        let bytes = Encode!(&stored).unwrap();
+        assert_eq!(
+            Decode!(&bytes, Mime).unwrap(),
+            Mime::Document(MimeDocument::Pdf)
+        );
+        let json = serde_json::to_string(&stored).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Mime>(&json).unwrap(),
+            Mime::Document(MimeDocument::Pdf)
+        );
+
+        for mime in [
+            Mime::Other("application/zip".to_string()),
+            Mime::Other("not a mime".to_string()),
+            Mime::Document(MimeDocument::Pdf),
+            Mime::Image(MimeImage::Png),
+        ] {
+            let bytes = Encode!(&mime).unwrap();
+            assert_eq!(Decode!(&bytes, Mime).unwrap(), mime);
+        }
+        assert_eq!(
+            "x-docx".parse::<MimeDocument>(),
+            Err(MimeError::UnknownDocument("x-docx".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_content_type() {
+        let content_type = "Text/HTML; Charset=UTF-8; title=\"a \\\"b\\\"; c\""
+            .parse::<ContentType>()
+            .unwrap();
+        assert_eq!(content_type.mime, Mime::Other("text/html".to_string()));
+        assert_eq!(content_type.param("charset"), Some("UTF-8"));
+        assert_eq!(content_type.param("title"), Some("a \"b\"; c"));
+        assert_eq!(
+            content_type.to_string(),
+            "text/html; charset=UTF-8; title=\"a \\\"b\\\"; c\""
+        );
+        assert_eq!(content_type.to_string().parse(), Ok(content_type));
+
+        assert_eq!(
+            "image/png; charset".parse::<ContentType>(),
+            Err(MimeError::InvalidParameter("charset".to_string()))
+        );
+        assert_eq!(
+            "image/png; title=\"a".parse::<ContentType>(),
+            Err(MimeError::InvalidParameter("title".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_mime_crate_interop() {
+        assert_eq!(Mime::from(&::mime::IMAGE_SVG), Mime::Image(MimeImage::Svg));
+        assert_eq!(
+            Mime::from(&::mime::IMAGE_JPEG),
+            Mime::Image(MimeImage::Jpeg)
+        );
+
+        let mime = "video/mp4; codecs=\"avc1.42E01E, mp4a.40.2\""
+            .parse::<::mime::Mime>()
+            .unwrap();
+        let content_type = ContentType::from(&mime);
+        assert_eq!(content_type.mime, Mime::Video(MimeVideo::Mp4));
+        assert_eq!(content_type.param("codecs"), Some("avc1.42E01E, mp4a.40.2"));
+        assert_eq!(::mime::Mime::try_from(content_type), Ok(mime));
+
+        assert_eq!(
+            ::mime::Mime::try_from(&Mime::Audio(MimeAudio::Ogg)),
+            Ok(::mime::Mime::from_str("audio/ogg").unwrap())
+        );
+        assert!(::mime::Mime::try_from(&Mime::Other("not a mime".to_string())).is_err());
+    }
+}
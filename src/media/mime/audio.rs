@@ -1,6 +1,7 @@
+use super::{ftyp_brands, parse_token, MimeError};
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub enum MimeAudio {
@@ -17,6 +18,48 @@ pub enum MimeAudio {
     Mp4,
 }
 
+impl MimeAudio {
+    /// Detect FLAC, WAV, AIFF, MP3, AAC, Ogg and M4A audio from their leading bytes
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'f', b'L', b'a', b'C', ..] => Some(MimeAudio::Flac),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => {
+                Some(MimeAudio::Wav)
+            }
+            [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C', ..] => {
+                Some(MimeAudio::Aiff)
+            }
+            [b'I', b'D', b'3', ..] => Some(MimeAudio::Mp3),
+            // ADTS frame header, with layer 0
+            [0xFF, 0xF0..=0xFF, ..] if bytes[1] & 0x06 == 0 => Some(MimeAudio::Aac),
+            // MPEG audio frame header, with a layer other than the reserved 0
+            [0xFF, 0xE0..=0xFF, ..] if bytes[1] & 0x06 != 0 => Some(MimeAudio::Mp3),
+            [b'O', b'g', b'g', b'S', ..] => Some(MimeAudio::Ogg),
+            _ => match ftyp_brands(bytes).first().copied()? {
+                b"M4A " | b"M4B " => Some(MimeAudio::Mp4),
+                _ => None,
+            },
+        }
+    }
+
+    /// Get the usual file extension, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeAudio::Other(_) => None,
+            MimeAudio::Aac => Some("aac"),
+            MimeAudio::Mp3 => Some("mp3"),
+            MimeAudio::Ogg => Some("ogg"),
+            MimeAudio::Wav => Some("wav"),
+            MimeAudio::Webm => Some("weba"),
+            MimeAudio::Flac => Some("flac"),
+            MimeAudio::Alac => Some("m4a"),
+            MimeAudio::Aiff => Some("aiff"),
+            MimeAudio::Opus => Some("opus"),
+            MimeAudio::Mp4 => Some("m4a"),
+        }
+    }
+}
+
 impl fmt::Display for MimeAudio {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -34,3 +77,25 @@ impl fmt::Display for MimeAudio {
         }
     }
 }
+
+impl FromStr for MimeAudio {
+    type Err = MimeError;
+
+    /// Parse a subtype, ignoring case and accepting common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subtype = parse_token(s)?;
+        Ok(match subtype.as_str() {
+            "aac" => MimeAudio::Aac,
+            "mp3" | "mpeg" => MimeAudio::Mp3,
+            "ogg" => MimeAudio::Ogg,
+            "wav" | "wave" | "x-wav" | "vnd.wave" => MimeAudio::Wav,
+            "webm" => MimeAudio::Webm,
+            "flac" | "x-flac" => MimeAudio::Flac,
+            "alac" => MimeAudio::Alac,
+            "aiff" | "x-aiff" => MimeAudio::Aiff,
+            "opus" => MimeAudio::Opus,
+            "mp4" => MimeAudio::Mp4,
+            _ => MimeAudio::Other(subtype),
+        })
+    }
+}
@@ -0,0 +1,272 @@
+use super::{ContentType, Mime, MimeAudio, MimeError, MimeVideo};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// RFC 6381 codec string such as `avc1.42E01E`, `av01.0.04M.08` or `opus`, as in the `codecs`
+/// parameter of a MIME type
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Codec(String);
+
+impl Codec {
+    pub fn new<T: AsRef<str>>(codec: T) -> Result<Self, MimeError> {
+        let codec = codec.as_ref().trim();
+        let is_valid = codec.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+        if !is_valid {
+            return Err(MimeError::InvalidCodec(codec.to_string()));
+        }
+        Ok(Self(codec.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the codec family from the four-character code before the first `.`
+    pub fn kind(&self) -> CodecKind {
+        let mut parts = self.0.split('.');
+        let fourcc = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        match fourcc.as_str() {
+            "avc1" | "avc3" => CodecKind::Avc,
+            "hev1" | "hvc1" => CodecKind::Hevc,
+            "av01" => CodecKind::Av1,
+            "vp8" | "vp08" => CodecKind::Vp8,
+            "vp9" | "vp09" => CodecKind::Vp9,
+            "theora" => CodecKind::Theora,
+            "mp4a" => match parts.next().map(str::to_ascii_lowercase).as_deref() {
+                // MPEG-4 audio, whose object type follows
+                Some("40" | "66" | "67" | "68") => CodecKind::Aac,
+                Some("69") | Some("6b") => CodecKind::Mp3,
+                _ => CodecKind::Other,
+            },
+            "mp3" => CodecKind::Mp3,
+            "opus" => CodecKind::Opus,
+            "vorbis" => CodecKind::Vorbis,
+            "flac" => CodecKind::Flac,
+            _ => CodecKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Codec {
+    type Err = MimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Codec {
+    type Error = MimeError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+/// Codec family, independent of profile and level
+#[derive(
+    CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, PartialOrd, Ord,
+)]
+pub enum CodecKind {
+    Other,
+    Avc,
+    Hevc,
+    Av1,
+    Vp8,
+    Vp9,
+    Theora,
+    Aac,
+    Mp3,
+    Opus,
+    Vorbis,
+    Flac,
+}
+
+impl CodecKind {
+    /// Check whether the codec can be stored in the container. Unknown containers allow any
+    /// codec.
+    pub fn fits(&self, container: &Mime) -> bool {
+        use CodecKind::*;
+
+        match container {
+            Mime::Video(MimeVideo::Webm) | Mime::Audio(MimeAudio::Webm) => {
+                matches!(self, Vp8 | Vp9 | Av1 | Opus | Vorbis)
+            }
+            Mime::Video(MimeVideo::Ogg) | Mime::Audio(MimeAudio::Ogg) => {
+                matches!(self, Theora | Opus | Vorbis | Flac)
+            }
+            Mime::Video(MimeVideo::Mp4) | Mime::Audio(MimeAudio::Mp4) => {
+                matches!(self, Avc | Hevc | Av1 | Vp9 | Aac | Mp3 | Opus | Flac)
+            }
+            Mime::Video(MimeVideo::Quicktime) => matches!(self, Avc | Hevc | Aac | Mp3),
+            _ => *self != Other,
+        }
+    }
+}
+
+impl ContentType {
+    /// Parse the `codecs` parameter, which is empty if missing
+    pub fn codecs(&self) -> Result<Vec<Codec>, MimeError> {
+        match self.param("codecs") {
+            Some(codecs) => codecs.split(',').map(Codec::new).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Set the `codecs` parameter, removing it if there are no codecs
+    pub fn with_codecs(mut self, codecs: &[Codec]) -> Self {
+        self.params.retain(|(name, _)| name != "codecs");
+        if !codecs.is_empty() {
+            let codecs = codecs.iter().map(Codec::as_str).collect::<Vec<_>>();
+            self.params.push(("codecs".to_string(), codecs.join(", ")));
+        }
+        self
+    }
+}
+
+/// Answer of [`PlaybackSupport::can_play`], as of `HTMLMediaElement.canPlayType`
+#[derive(CandidType, Copy, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum CanPlay {
+    No,
+    Maybe,
+    Probably,
+}
+
+impl CanPlay {
+    /// Get the string returned by `canPlayType`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CanPlay::No => "",
+            CanPlay::Maybe => "maybe",
+            CanPlay::Probably => "probably",
+        }
+    }
+}
+
+/// Containers and codecs that a frontend can play
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PlaybackSupport {
+    pub containers: Vec<Mime>,
+    pub codecs: Vec<CodecKind>,
+}
+
+impl PlaybackSupport {
+    /// Check whether media of the content type can be played.
+    ///
+    /// Without a `codecs` parameter only the container is known, so the answer is at most
+    /// [`CanPlay::Maybe`].
+    pub fn can_play(&self, content_type: &ContentType) -> CanPlay {
+        if !self.containers.contains(&content_type.mime) {
+            return CanPlay::No;
+        }
+        let Ok(codecs) = content_type.codecs() else {
+            return CanPlay::No;
+        };
+        if codecs.is_empty() {
+            return CanPlay::Maybe;
+        }
+        let is_playable = codecs.iter().all(|codec| {
+            let kind = codec.kind();
+            self.codecs.contains(&kind) && kind.fits(&content_type.mime)
+        });
+        if is_playable {
+            CanPlay::Probably
+        } else {
+            CanPlay::No
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec_kind() {
+        for (codec, kind) in [
+            ("avc1.42E01E", CodecKind::Avc),
+            ("hvc1.1.6.L93.B0", CodecKind::Hevc),
+            ("av01.0.04M.08", CodecKind::Av1),
+            ("vp09.00.10.08", CodecKind::Vp9),
+            ("vp8", CodecKind::Vp8),
+            ("mp4a.40.2", CodecKind::Aac),
+            ("mp4a.6B", CodecKind::Mp3),
+            ("Opus", CodecKind::Opus),
+            ("flac", CodecKind::Flac),
+            ("xyz1.2", CodecKind::Other),
+        ] {
+            assert_eq!(Codec::new(codec).unwrap().kind(), kind, "{}", codec);
+        }
+        for codec in ["", "avc1..42", "avc1,mp4a", "\"vp8\""] {
+            assert_eq!(
+                Codec::new(codec),
+                Err(MimeError::InvalidCodec(codec.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_type_codecs() {
+        let content_type = "video/mp4; codecs=\"avc1.42E01E, mp4a.40.2\""
+            .parse::<ContentType>()
+            .unwrap();
+        assert_eq!(content_type.mime, Mime::Video(MimeVideo::Mp4));
+        assert_eq!(
+            content_type.codecs(),
+            Ok(vec![
+                Codec::new("avc1.42E01E").unwrap(),
+                Codec::new("mp4a.40.2").unwrap()
+            ])
+        );
+
+        let content_type = ContentType::from(Mime::Video(MimeVideo::Webm))
+            .with_codecs(&[Codec::new("vp9").unwrap(), Codec::new("opus").unwrap()]);
+        assert_eq!(content_type.to_string(), "video/webm; codecs=\"vp9, opus\"");
+        assert_eq!(content_type.with_codecs(&[]).to_string(), "video/webm");
+    }
+
+    #[test]
+    fn test_can_play() {
+        let support = PlaybackSupport {
+            containers: vec![
+                Mime::Video(MimeVideo::Mp4),
+                Mime::Video(MimeVideo::Webm),
+                Mime::Audio(MimeAudio::Ogg),
+            ],
+            codecs: vec![
+                CodecKind::Avc,
+                CodecKind::Vp9,
+                CodecKind::Aac,
+                CodecKind::Opus,
+            ],
+        };
+        let can_play = |content_type: &str| support.can_play(&content_type.parse().unwrap());
+
+        assert_eq!(can_play("video/mp4"), CanPlay::Maybe);
+        assert_eq!(
+            can_play("video/mp4; codecs=\"avc1.42E01E, mp4a.40.2\""),
+            CanPlay::Probably
+        );
+        assert_eq!(
+            can_play("video/webm; codecs=\"vp09.00.10.08, opus\""),
+            CanPlay::Probably
+        );
+        assert_eq!(can_play("audio/ogg; codecs=opus"), CanPlay::Probably);
+        // AV1 is not supported, and AVC cannot be in WebM
+        assert_eq!(can_play("video/mp4; codecs=av01.0.04M.08"), CanPlay::No);
+        assert_eq!(can_play("video/webm; codecs=avc1.42E01E"), CanPlay::No);
+        assert_eq!(can_play("video/quicktime"), CanPlay::No);
+        assert_eq!(can_play("video/mp4; codecs=\"avc1.42E01E,,\""), CanPlay::No);
+        assert_eq!(CanPlay::Maybe.as_str(), "maybe");
+    }
+}
@@ -0,0 +1,317 @@
+use super::{Mime, MimeAudio, MimeDocument, MimeImage, MimeModel, MimeVideo};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+impl Mime {
+    /// Get the type of a file extension, without the dot and ignoring case
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "png" => Mime::Image(MimeImage::Png),
+            "apng" => Mime::Image(MimeImage::Apng),
+            "jpg" | "jpeg" | "jpe" | "jfif" => Mime::Image(MimeImage::Jpeg),
+            "gif" => Mime::Image(MimeImage::Gif),
+            "webp" => Mime::Image(MimeImage::Webp),
+            "avif" => Mime::Image(MimeImage::Avif),
+            "heif" | "heic" => Mime::Image(MimeImage::Heif),
+            "svg" => Mime::Image(MimeImage::Svg),
+            "tif" | "tiff" => Mime::Image(MimeImage::Tiff),
+            "mp4" | "m4v" => Mime::Video(MimeVideo::Mp4),
+            "webm" => Mime::Video(MimeVideo::Webm),
+            "ogv" => Mime::Video(MimeVideo::Ogg),
+            "mov" => Mime::Video(MimeVideo::Quicktime),
+            "mpg" | "mpeg" => Mime::Video(MimeVideo::Mpeg),
+            "mp3" => Mime::Audio(MimeAudio::Mp3),
+            "aac" => Mime::Audio(MimeAudio::Aac),
+            "m4a" => Mime::Audio(MimeAudio::Mp4),
+            "ogg" | "oga" => Mime::Audio(MimeAudio::Ogg),
+            "opus" => Mime::Audio(MimeAudio::Opus),
+            "wav" => Mime::Audio(MimeAudio::Wav),
+            "weba" => Mime::Audio(MimeAudio::Webm),
+            "flac" => Mime::Audio(MimeAudio::Flac),
+            "aif" | "aiff" => Mime::Audio(MimeAudio::Aiff),
+            "gltf" => Mime::Model(MimeModel::GltfJson),
+            "glb" => Mime::Model(MimeModel::GltfBinary),
+            "usdz" => Mime::Model(MimeModel::Usdz),
+            "pdf" => Mime::Document(MimeDocument::Pdf),
+            _ => return None,
+        })
+    }
+
+    /// Get the type of a file from the extension of its name
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        Self::from_extension(extension)
+    }
+
+    /// Detect the type of a file from its leading bytes, see [`Self::is_sniffable`]
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        MimeImage::sniff(bytes)
+            .map(Mime::Image)
+            .or_else(|| MimeVideo::sniff(bytes).map(Mime::Video))
+            .or_else(|| MimeAudio::sniff(bytes).map(Mime::Audio))
+            .or_else(|| MimeModel::sniff(bytes).map(Mime::Model))
+            .or_else(|| MimeDocument::sniff(bytes).map(Mime::Document))
+    }
+
+    /// Check whether files of this type are recognized by [`Self::sniff`], so that a file
+    /// declared as this type must have matching bytes
+    pub fn is_sniffable(&self) -> bool {
+        match self {
+            Mime::Image(subtype) => !matches!(subtype, MimeImage::Tiff | MimeImage::Other(_)),
+            Mime::Video(subtype) => matches!(
+                subtype,
+                MimeVideo::Mp4
+                    | MimeVideo::Webm
+                    | MimeVideo::Ogg
+                    | MimeVideo::Quicktime
+                    | MimeVideo::Mpeg
+            ),
+            Mime::Audio(subtype) => !matches!(subtype, MimeAudio::Alac | MimeAudio::Other(_)),
+            Mime::Model(subtype) => matches!(subtype, MimeModel::GltfBinary | MimeModel::Usdz),
+            Mime::Document(_) => true,
+            Mime::Other(_) => false,
+        }
+    }
+
+    /// Check whether a file declared as this type may have the detected type, e.g. an APNG
+    /// image is detected as PNG and a WebM audio as WebM video
+    pub fn is_compatible_with(&self, detected: &Mime) -> bool {
+        use Mime::{Audio, Image, Video};
+
+        self == detected
+            || matches!(
+                (self, detected),
+                (Image(MimeImage::Apng), Image(MimeImage::Png))
+                    | (Audio(MimeAudio::Webm), Video(MimeVideo::Webm))
+                    | (Audio(MimeAudio::Opus), Audio(MimeAudio::Ogg))
+                    | (Video(MimeVideo::Ogg), Audio(MimeAudio::Ogg))
+                    | (
+                        Audio(MimeAudio::Mp4 | MimeAudio::Aac),
+                        Audio(MimeAudio::Mp4) | Video(MimeVideo::Mp4)
+                    )
+                    | (Video(MimeVideo::Mp4), Audio(MimeAudio::Mp4))
+            )
+    }
+
+    /// Detect the type of an uploaded file from its bytes, falling back to its extension for
+    /// types that cannot be sniffed.
+    ///
+    /// Fail if the extension does not match the bytes.
+    pub fn detect(filename: &str, bytes: &[u8]) -> Result<Self, MimeDetectionError> {
+        let sniffed = Self::sniff(bytes);
+        match (Self::from_filename(filename), sniffed) {
+            (Some(declared), detected)
+                if (declared.is_sniffable() || detected.is_some())
+                    && !detected
+                        .as_ref()
+                        .is_some_and(|detected| declared.is_compatible_with(detected)) =>
+            {
+                Err(MimeDetectionError::Mismatch { declared, detected })
+            }
+            (_, Some(detected)) => Ok(detected),
+            (Some(declared), None) => Ok(declared),
+            (None, None) => Err(MimeDetectionError::Unknown),
+        }
+    }
+
+    /// Check that an uploaded file is of this declared type, returning the detected type
+    pub fn verify(&self, filename: &str, bytes: &[u8]) -> Result<Self, MimeDetectionError> {
+        let detected = match Self::detect(filename, bytes) {
+            Err(MimeDetectionError::Unknown) if !self.is_sniffable() => return Ok(self.clone()),
+            Err(MimeDetectionError::Unknown) => None,
+            result => Some(result?),
+        };
+        match detected {
+            Some(detected) if self.is_compatible_with(&detected) => Ok(detected),
+            detected => Err(MimeDetectionError::Mismatch {
+                declared: self.clone(),
+                detected,
+            }),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MimeDetectionError {
+    #[error("File type is unknown")]
+    Unknown,
+    #[error(
+        "File is declared as {declared} but is {}",
+        detected.as_ref().map_or("not recognized".to_string(), Mime::to_string)
+    )]
+    Mismatch {
+        declared: Mime,
+        /// `None` if the bytes are not recognized
+        detected: Option<Mime>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let mut bytes = ((16 + 4 * compatible.len()) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(major);
+        bytes.extend_from_slice(&[0; 4]);
+        compatible
+            .iter()
+            .for_each(|brand| bytes.extend_from_slice(*brand));
+        bytes
+    }
+
+    #[test]
+    fn test_sniff() {
+        let cases: Vec<(Vec<u8>, Mime)> = vec![
+            (PNG.to_vec(), Mime::Image(MimeImage::Png)),
+            (b"\xff\xd8\xff\xdb".to_vec(), Mime::Image(MimeImage::Jpeg)),
+            (b"GIF89a".to_vec(), Mime::Image(MimeImage::Gif)),
+            (b"RIFF\0\0\0\0WEBPVP8 ".to_vec(), Mime::Image(MimeImage::Webp)),
+            (ftyp(b"avif", &[b"mif1"]), Mime::Image(MimeImage::Avif)),
+            (ftyp(b"mif1", &[b"avif"]), Mime::Image(MimeImage::Avif)),
+            (ftyp(b"heic", &[b"mif1"]), Mime::Image(MimeImage::Heif)),
+            (
+                b"\xef\xbb\xbf<?xml version=\"1.0\"?>\n<!-- logo -->\n<!DOCTYPE svg>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>".to_vec(),
+                Mime::Image(MimeImage::Svg),
+            ),
+            (ftyp(b"isom", &[b"iso2", b"mp41"]), Mime::Video(MimeVideo::Mp4)),
+            (ftyp(b"qt  ", &[]), Mime::Video(MimeVideo::Quicktime)),
+            (
+                b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm".to_vec(),
+                Mime::Video(MimeVideo::Webm),
+            ),
+            (
+                b"OggS\0\x02\0\0\0\0\0\0\0\0\x80theora".to_vec(),
+                Mime::Video(MimeVideo::Ogg),
+            ),
+            (b"OggS\0\x02\0\0\0\0\0\0\0\0\x01vorbis".to_vec(), Mime::Audio(MimeAudio::Ogg)),
+            (ftyp(b"M4A ", &[b"isom"]), Mime::Audio(MimeAudio::Mp4)),
+            (b"fLaC\0\0\0\x22".to_vec(), Mime::Audio(MimeAudio::Flac)),
+            (b"RIFF\0\0\0\0WAVEfmt ".to_vec(), Mime::Audio(MimeAudio::Wav)),
+            (b"ID3\x04\0".to_vec(), Mime::Audio(MimeAudio::Mp3)),
+            (b"\xff\xfb\x90\x64".to_vec(), Mime::Audio(MimeAudio::Mp3)),
+            (b"\xff\xf1\x50\x80".to_vec(), Mime::Audio(MimeAudio::Aac)),
+            (b"glTF\x02\0\0\0".to_vec(), Mime::Model(MimeModel::GltfBinary)),
+            (
+                b"PK\x03\x04\x0a\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x0a\0\0\0chair.usdc".to_vec(),
+                Mime::Model(MimeModel::Usdz),
+            ),
+            (b"%PDF-1.7\n".to_vec(), Mime::Document(MimeDocument::Pdf)),
+        ];
+        for (bytes, mime) in cases {
+            assert_eq!(Mime::sniff(&bytes), Some(mime.clone()), "{}", mime);
+            assert!(mime.is_sniffable(), "{}", mime);
+        }
+
+        assert_eq!(Mime::sniff(b""), None);
+        assert_eq!(Mime::sniff(b"<html><svg></svg></html>"), None);
+        assert_eq!(Mime::sniff(b"<svgs>"), None);
+        assert_eq!(Mime::sniff(&ftyp(b"crx ", &[])), None);
+        assert_eq!(
+            Mime::sniff(
+                b"PK\x03\x04\x0a\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x08\0\0\0doc.docx"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_filename() {
+        assert_eq!(
+            Mime::from_filename("photo.final.JPG"),
+            Some(Mime::Image(MimeImage::Jpeg))
+        );
+        assert_eq!(
+            Mime::from_filename("clip.webm"),
+            Some(Mime::Video(MimeVideo::Webm))
+        );
+        assert_eq!(
+            Mime::from_filename("song.m4a"),
+            Some(Mime::Audio(MimeAudio::Mp4))
+        );
+        assert_eq!(Mime::from_filename("README"), None);
+        assert_eq!(Mime::from_filename("archive.zip"), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let png = Mime::Image(MimeImage::Png);
+        assert_eq!(Mime::detect("photo.png", PNG), Ok(png.clone()));
+        assert_eq!(Mime::detect("photo", PNG), Ok(png.clone()));
+        assert_eq!(
+            Mime::detect("scan.tiff", b"II*\0"),
+            Ok(Mime::Image(MimeImage::Tiff))
+        );
+        assert_eq!(
+            Mime::detect("photo.jpg", PNG),
+            Err(MimeDetectionError::Mismatch {
+                declared: Mime::Image(MimeImage::Jpeg),
+                detected: Some(png.clone())
+            })
+        );
+        assert_eq!(
+            Mime::detect("photo.png", b"<html></html>"),
+            Err(MimeDetectionError::Mismatch {
+                declared: png.clone(),
+                detected: None
+            })
+        );
+        assert_eq!(
+            Mime::detect("notes", b"hello"),
+            Err(MimeDetectionError::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let png = Mime::Image(MimeImage::Png);
+        assert_eq!(png.verify("photo.png", PNG), Ok(png.clone()));
+        assert_eq!(
+            Mime::Image(MimeImage::Apng).verify("anim.apng", PNG),
+            Ok(png.clone())
+        );
+        assert_eq!(
+            Mime::Audio(MimeAudio::Webm).verify(
+                "voice.weba",
+                b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm"
+            ),
+            Ok(Mime::Video(MimeVideo::Webm))
+        );
+        assert_eq!(
+            Mime::Image(MimeImage::Gif).verify("photo", PNG),
+            Err(MimeDetectionError::Mismatch {
+                declared: Mime::Image(MimeImage::Gif),
+                detected: Some(png.clone())
+            })
+        );
+        assert_eq!(
+            png.verify("photo", b"<script></script>"),
+            Err(MimeDetectionError::Mismatch {
+                declared: png.clone(),
+                detected: None
+            })
+        );
+        let zip = Mime::Other("application/zip".to_string());
+        assert_eq!(zip.verify("manual", b"PK\x03\x04"), Ok(zip.clone()));
+        let pdf = Mime::Document(MimeDocument::Pdf);
+        assert_eq!(pdf.verify("manual.pdf", b"%PDF-1.7"), Ok(pdf.clone()));
+        assert_eq!(
+            pdf.verify("manual.pdf", b"<html>"),
+            Err(MimeDetectionError::Mismatch {
+                declared: pdf,
+                detected: None
+            })
+        );
+        assert_eq!(
+            MimeDetectionError::Mismatch {
+                declared: png,
+                detected: None
+            }
+            .to_string(),
+            "File is declared as image/png but is not recognized"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use super::{parse_token, MimeError};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Document such as a manual, as an `application/*` subtype.
+///
+/// The list is closed, since other `application/*` types are [`super::Mime::Other`].
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum MimeDocument {
+    Pdf,
+}
+
+impl MimeDocument {
+    /// Detect PDF documents from their leading bytes
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        bytes.starts_with(b"%PDF-").then_some(MimeDocument::Pdf)
+    }
+
+    /// Get the usual file extension, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeDocument::Pdf => Some("pdf"),
+        }
+    }
+}
+
+impl fmt::Display for MimeDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MimeDocument::Pdf => write!(f, "pdf"),
+        }
+    }
+}
+
+impl FromStr for MimeDocument {
+    type Err = MimeError;
+
+    /// Parse a subtype, ignoring case and accepting common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subtype = parse_token(s)?;
+        match subtype.as_str() {
+            "pdf" | "x-pdf" => Ok(MimeDocument::Pdf),
+            _ => Err(MimeError::UnknownDocument(subtype)),
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use super::{ftyp_brands, parse_token, MimeError};
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 #[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub enum MimeImage {
@@ -16,6 +17,76 @@ pub enum MimeImage {
     Heif,
 }
 
+impl MimeImage {
+    /// Detect PNG, JPEG, GIF, WebP, AVIF, HEIF and SVG images from their leading bytes.
+    /// APNG images are detected as PNG.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n', ..] => Some(MimeImage::Png),
+            [0xFF, 0xD8, 0xFF, ..] => Some(MimeImage::Jpeg),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(MimeImage::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => {
+                Some(MimeImage::Webp)
+            }
+            _ => {
+                let brands = ftyp_brands(bytes);
+                match brands.first().copied() {
+                    Some(b"avif" | b"avis") => Some(MimeImage::Avif),
+                    Some(b"mif1" | b"msf1") if brands.contains(&b"avif") => Some(MimeImage::Avif),
+                    Some(
+                        b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1"
+                        | b"msf1",
+                    ) => Some(MimeImage::Heif),
+                    Some(_) => None,
+                    None => is_svg(bytes).then_some(MimeImage::Svg),
+                }
+            }
+        }
+    }
+
+    /// Get the usual file extension, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeImage::Other(_) => None,
+            MimeImage::Gif => Some("gif"),
+            MimeImage::Jpeg => Some("jpg"),
+            MimeImage::Png => Some("png"),
+            MimeImage::Svg => Some("svg"),
+            MimeImage::Tiff => Some("tiff"),
+            MimeImage::Webp => Some("webp"),
+            MimeImage::Apng => Some("apng"),
+            MimeImage::Avif => Some("avif"),
+            MimeImage::Heif => Some("heif"),
+        }
+    }
+}
+
+/// Check whether the text starts with an `<svg>` element, after any XML declaration, comments
+/// and doctype
+fn is_svg(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    let mut text = text.trim_start_matches('\u{FEFF}');
+    loop {
+        text = text.trim_start();
+        let end = if text.starts_with("<?") {
+            "?>"
+        } else if text.starts_with("<!--") {
+            "-->"
+        } else if text.starts_with("<!") {
+            ">"
+        } else {
+            break;
+        };
+        match text.find(end) {
+            Some(i) => text = &text[i + end.len()..],
+            None => return false,
+        }
+    }
+    text.strip_prefix("<svg")
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+}
+
 impl fmt::Display for MimeImage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -32,3 +103,24 @@ impl fmt::Display for MimeImage {
         }
     }
 }
+
+impl FromStr for MimeImage {
+    type Err = MimeError;
+
+    /// Parse a subtype, ignoring case and accepting common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subtype = parse_token(s)?;
+        Ok(match subtype.as_str() {
+            "gif" => MimeImage::Gif,
+            "jpeg" | "jpg" | "pjpeg" => MimeImage::Jpeg,
+            "png" => MimeImage::Png,
+            "svg+xml" | "svg" => MimeImage::Svg,
+            "tiff" => MimeImage::Tiff,
+            "webp" => MimeImage::Webp,
+            "apng" | "vnd.mozilla.apng" => MimeImage::Apng,
+            "avif" => MimeImage::Avif,
+            "heif" => MimeImage::Heif,
+            _ => MimeImage::Other(subtype),
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use super::{parse_token, MimeError};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// 3D model, e.g. for AR previews of an item
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum MimeModel {
+    Other(String),
+    /// glTF as JSON
+    GltfJson,
+    /// glTF as a single binary file (GLB)
+    GltfBinary,
+    /// USDZ, as used by AR Quick Look
+    Usdz,
+}
+
+impl MimeModel {
+    /// Detect GLB and USDZ models from their leading bytes
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'g', b'l', b'T', b'F', ..] => Some(MimeModel::GltfBinary),
+            // A USDZ is an uncompressed ZIP whose first file is a USD layer
+            [b'P', b'K', 0x03, 0x04, ..] if bytes.len() >= 30 => {
+                let name_length = u16::from_le_bytes([bytes[26], bytes[27]]) as usize;
+                let name = bytes.get(30..30 + name_length)?;
+                let extension = name.rsplit(|&byte| byte == b'.').next()?;
+                [b"usd".as_slice(), b"usda", b"usdc"]
+                    .iter()
+                    .any(|usd| extension.eq_ignore_ascii_case(usd))
+                    .then_some(MimeModel::Usdz)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the usual file extension, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeModel::Other(_) => None,
+            MimeModel::GltfJson => Some("gltf"),
+            MimeModel::GltfBinary => Some("glb"),
+            MimeModel::Usdz => Some("usdz"),
+        }
+    }
+}
+
+impl fmt::Display for MimeModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MimeModel::Other(other) => write!(f, "{}", other),
+            MimeModel::GltfJson => write!(f, "gltf+json"),
+            MimeModel::GltfBinary => write!(f, "gltf-binary"),
+            MimeModel::Usdz => write!(f, "vnd.usdz+zip"),
+        }
+    }
+}
+
+impl FromStr for MimeModel {
+    type Err = MimeError;
+
+    /// Parse a subtype, ignoring case and accepting common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subtype = parse_token(s)?;
+        Ok(match subtype.as_str() {
+            "gltf+json" => MimeModel::GltfJson,
+            "gltf-binary" => MimeModel::GltfBinary,
+            "vnd.usdz+zip" | "vnd.pixar.usdz" | "usdz" => MimeModel::Usdz,
+            _ => MimeModel::Other(subtype),
+        })
+    }
+}
@@ -1,20 +1,63 @@
-use candid::CandidType;
-use serde::{Deserialize, Serialize};
-use std::fmt;
+use super::{contains, ftyp_brands, parse_token, MimeError};
+use candid::{
+    types::{Serializer, Type},
+    CandidType,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{fmt, str::FromStr};
 
-#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+/// Container format of a video. The codecs inside are described by [`super::Codec`].
+///
+/// The Candid type still lists the codecs that used to be subtypes, so that stored data keeps
+/// decoding; they are read as the container they are usually in.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub enum MimeVideo {
     Other(String),
     Mp4,
-    Av1,
     Mpeg,
     Ogg,
     Quicktime,
     Webm,
-    Vp8,
-    Vp9,
-    H264,
-    H265,
+}
+
+impl MimeVideo {
+    /// Detect MP4, QuickTime, WebM, Matroska, Ogg Theora and MPEG videos from their leading bytes
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => {
+                Some(if contains(&bytes[..bytes.len().min(64)], b"webm") {
+                    MimeVideo::Webm
+                } else {
+                    MimeVideo::Other("x-matroska".to_string())
+                })
+            }
+            [b'O', b'g', b'g', b'S', ..]
+                if contains(&bytes[..bytes.len().min(128)], b"\x80theora") =>
+            {
+                Some(MimeVideo::Ogg)
+            }
+            [0x00, 0x00, 0x01, 0xBA, ..] => Some(MimeVideo::Mpeg),
+            [_, _, _, _, b'm', b'o', b'o', b'v', ..] => Some(MimeVideo::Quicktime),
+            _ => match ftyp_brands(bytes).first().copied()? {
+                b"qt  " => Some(MimeVideo::Quicktime),
+                b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1"
+                | b"dash" | b"M4V " | b"mmp4" => Some(MimeVideo::Mp4),
+                _ => None,
+            },
+        }
+    }
+
+    /// Get the usual file extension, without the dot
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeVideo::Mp4 => Some("mp4"),
+            MimeVideo::Mpeg => Some("mpeg"),
+            MimeVideo::Ogg => Some("ogv"),
+            MimeVideo::Quicktime => Some("mov"),
+            MimeVideo::Webm => Some("webm"),
+            MimeVideo::Other(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for MimeVideo {
@@ -22,15 +65,175 @@ impl fmt::Display for MimeVideo {
         match self {
             MimeVideo::Other(other) => write!(f, "{}", other),
             MimeVideo::Mp4 => write!(f, "mp4"),
-            MimeVideo::Av1 => write!(f, "AV1"),
             MimeVideo::Mpeg => write!(f, "mpeg"),
             MimeVideo::Ogg => write!(f, "ogg"),
             MimeVideo::Quicktime => write!(f, "quicktime"),
             MimeVideo::Webm => write!(f, "webm"),
-            MimeVideo::Vp8 => write!(f, "VP8"),
-            MimeVideo::Vp9 => write!(f, "VP9"),
-            MimeVideo::H264 => write!(f, "H264"),
-            MimeVideo::H265 => write!(f, "H265"),
+        }
+    }
+}
+
+#[allow(deprecated)]
+mod repr {
+    use candid::CandidType;
+    use serde::{Deserialize, Serialize};
+
+    /// Candid and serde shape of [`super::MimeVideo`], including the codecs that used to be
+    /// subtypes
+    #[derive(CandidType, Serialize, Deserialize)]
+    pub(super) enum Repr {
+        Other(String),
+        Mp4,
+        #[deprecated(note = "AV1 is a codec, read as `Mp4`")]
+        Av1,
+        Mpeg,
+        Ogg,
+        Quicktime,
+        Webm,
+        #[deprecated(note = "VP8 is a codec, read as `Webm`")]
+        Vp8,
+        #[deprecated(note = "VP9 is a codec, read as `Webm`")]
+        Vp9,
+        #[deprecated(note = "H.264 is a codec, read as `Mp4`")]
+        H264,
+        #[deprecated(note = "H.265 is a codec, read as `Mp4`")]
+        H265,
+    }
+}
+
+use repr::Repr;
+
+impl From<&MimeVideo> for Repr {
+    fn from(mime: &MimeVideo) -> Self {
+        match mime {
+            MimeVideo::Other(other) => Repr::Other(other.clone()),
+            MimeVideo::Mp4 => Repr::Mp4,
+            MimeVideo::Mpeg => Repr::Mpeg,
+            MimeVideo::Ogg => Repr::Ogg,
+            MimeVideo::Quicktime => Repr::Quicktime,
+            MimeVideo::Webm => Repr::Webm,
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl From<Repr> for MimeVideo {
+    fn from(repr: Repr) -> Self {
+        match repr {
+            Repr::Other(other) => MimeVideo::Other(other),
+            Repr::Mp4 | Repr::Av1 | Repr::H264 | Repr::H265 => MimeVideo::Mp4,
+            Repr::Mpeg => MimeVideo::Mpeg,
+            Repr::Ogg => MimeVideo::Ogg,
+            Repr::Quicktime => MimeVideo::Quicktime,
+            Repr::Webm | Repr::Vp8 | Repr::Vp9 => MimeVideo::Webm,
+        }
+    }
+}
+
+impl CandidType for MimeVideo {
+    fn _ty() -> Type {
+        Repr::_ty()
+    }
+
+    fn idl_serialize<S: Serializer>(&self, serializer: S) -> Result<(), S::Error> {
+        Repr::from(self).idl_serialize(serializer)
+    }
+}
+
+impl Serialize for MimeVideo {
+    /// Goes through the same variants as [`Deserialize`], so that index-based formats such as
+    /// bincode read back the subtype that was written
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Repr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MimeVideo {
+    /// Also accept the codecs that used to be subtypes, as the container they are usually in
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Repr::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl FromStr for MimeVideo {
+    type Err = MimeError;
+
+    /// Parse a subtype, ignoring case and accepting common aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subtype = parse_token(s)?;
+        Ok(match subtype.as_str() {
+            "mp4" => MimeVideo::Mp4,
+            "mpeg" => MimeVideo::Mpeg,
+            "ogg" => MimeVideo::Ogg,
+            "quicktime" => MimeVideo::Quicktime,
+            "webm" => MimeVideo::Webm,
+            _ => MimeVideo::Other(subtype),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::mime::Mime;
+    use candid::{Decode, Encode};
+
+    #[test]
+    fn test_decode_codec_subtypes() {
+        for (json, mime) in [
+            ("\"Mp4\"", MimeVideo::Mp4),
+            ("\"H264\"", MimeVideo::Mp4),
+            ("\"Av1\"", MimeVideo::Mp4),
+            ("\"Vp9\"", MimeVideo::Webm),
+            (
+                "{\"Other\":\"x-flv\"}",
+                MimeVideo::Other("x-flv".to_string()),
+            ),
+        ] {
+            assert_eq!(serde_json::from_str::<MimeVideo>(json).unwrap(), mime);
+        }
+    }
+
+    #[test]
+    fn test_candid_codec_subtypes() {
+        /// Shape of [`MimeVideo`] when codecs were subtypes
+        #[derive(CandidType)]
+        enum LegacyMimeVideo {
+            Mp4,
+            Vp9,
+            H264,
+        }
+
+        for (legacy, mime) in [
+            (LegacyMimeVideo::Mp4, MimeVideo::Mp4),
+            (LegacyMimeVideo::H264, MimeVideo::Mp4),
+            (LegacyMimeVideo::Vp9, MimeVideo::Webm),
+        ] {
+            let bytes = Encode!(&legacy).unwrap();
+            assert_eq!(Decode!(&bytes, MimeVideo).unwrap(), mime);
+        }
+
+        let mime = MimeVideo::Other("x-flv".to_string());
+        let bytes = Encode!(&mime).unwrap();
+        assert_eq!(Decode!(&bytes, MimeVideo).unwrap(), mime);
+    }
+
+    #[test]
+    fn test_bincode_round_trip() {
+        for mime in [
+            MimeVideo::Other("x-flv".to_string()),
+            MimeVideo::Mp4,
+            MimeVideo::Mpeg,
+            MimeVideo::Ogg,
+            MimeVideo::Quicktime,
+            MimeVideo::Webm,
+        ] {
+            let bytes = bincode::serialize(&mime).unwrap();
+            assert_eq!(bincode::deserialize::<MimeVideo>(&bytes).unwrap(), mime);
+
+            let mime = Mime::Video(mime);
+            let bytes = bincode::serialize(&mime).unwrap();
+            assert_eq!(bincode::deserialize::<Mime>(&bytes).unwrap(), mime);
         }
     }
 }
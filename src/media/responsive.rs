@@ -0,0 +1,370 @@
+use super::{MediaData, MediaSrc, MediaSrcError, ResolverConfig};
+use crate::media::mime::{Mime, MimeImage};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Resized or re-encoded copy of an image, for `srcset`
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct ImageVariant {
+    pub src: MediaSrc,
+    pub format: MimeImage,
+    pub width: u32,
+    pub height: u32,
+    /// Pixel density in hundredths, e.g. 200 for `2x`, for images shown at a fixed size.
+    /// Variants without a density are described by their width.
+    pub density: Option<u16>,
+}
+
+impl ImageVariant {
+    /// Get the descriptor of the variant in `srcset`, e.g. `640w` or `1.5x`
+    fn descriptor(&self, use_density: bool) -> String {
+        match self.density {
+            Some(density) if use_density => {
+                let density = format!("{}.{:02}", density / 100, density % 100);
+                format!("{}x", density.trim_end_matches('0').trim_end_matches('.'))
+            }
+            _ => format!("{}w", self.width),
+        }
+    }
+}
+
+/// Rank of an image format, where lower is better
+fn format_rank(format: &MimeImage) -> u8 {
+    match format {
+        MimeImage::Avif => 0,
+        MimeImage::Webp => 1,
+        MimeImage::Jpeg => 2,
+        _ => 3,
+    }
+}
+
+/// `<source>` of a `<picture>`
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct PictureSource {
+    /// MIME type for the `type` attribute
+    pub mime: String,
+    pub srcset: String,
+}
+
+impl MediaData {
+    /// Get every variant, which is none for media stored before variants existed
+    pub fn image_variants(&self) -> &[ImageVariant] {
+        self.variants.as_deref().unwrap_or_default()
+    }
+
+    /// Get the variants in the format
+    pub fn variants_of(&self, format: &MimeImage) -> impl Iterator<Item = &ImageVariant> {
+        let format = format.clone();
+        self.image_variants()
+            .iter()
+            .filter(move |variant| variant.format == format)
+    }
+
+    /// Generate the `srcset` of the variants in the format, from the narrowest.
+    ///
+    /// Density descriptors are used only if every variant has a density, since a `srcset`
+    /// cannot mix them with width descriptors. Of variants with the same descriptor, only the
+    /// first is listed, since browsers drop a `srcset` with duplicate descriptors.
+    pub fn srcset(
+        &self,
+        format: &MimeImage,
+        config: &ResolverConfig,
+    ) -> Result<String, MediaSrcError> {
+        let mut variants = self.variants_of(format).collect::<Vec<_>>();
+        let use_density = variants.iter().all(|variant| variant.density.is_some());
+        if use_density {
+            variants.sort_by_key(|variant| (variant.density, variant.width));
+        } else {
+            variants.sort_by_key(|variant| variant.width);
+        }
+
+        let mut descriptors = Vec::new();
+        let candidates = variants
+            .into_iter()
+            .filter_map(|variant| {
+                let descriptor = variant.descriptor(use_density);
+                if descriptors.contains(&descriptor) {
+                    return None;
+                }
+                descriptors.push(descriptor.clone());
+                Some(
+                    variant
+                        .src
+                        .resolve(config)
+                        .map(|url| format!("{} {}", url, descriptor)),
+                )
+            })
+            .collect::<Result<Vec<_>, MediaSrcError>>()?;
+        Ok(candidates.join(", "))
+    }
+
+    /// Generate the `<source>`s of a `<picture>`, one for each format of the variants, with
+    /// AVIF first, then WebP, then JPEG
+    pub fn picture_sources(
+        &self,
+        config: &ResolverConfig,
+    ) -> Result<Vec<PictureSource>, MediaSrcError> {
+        let mut formats = Vec::<&MimeImage>::new();
+        for variant in self.image_variants() {
+            if !formats.contains(&&variant.format) {
+                formats.push(&variant.format);
+            }
+        }
+        formats.sort_by_key(|format| format_rank(format));
+
+        formats
+            .into_iter()
+            .map(|format| {
+                Ok(PictureSource {
+                    mime: Mime::Image(format.clone()).to_string(),
+                    srcset: self.srcset(format, config)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Pick the variant to show at the width in device pixels, among the accepted formats.
+    ///
+    /// Among the variants at least as wide, the best format (AVIF > WebP > JPEG > others) is
+    /// picked, and the narrowest in that format. If none is wide enough, the widest is picked.
+    /// `None` means that no variant is accepted and [`MediaData::src`] should be used.
+    pub fn pick_variant(&self, width: u32, accepted: &[MimeImage]) -> Option<&ImageVariant> {
+        let accepted = self
+            .image_variants()
+            .iter()
+            .filter(|variant| accepted.contains(&variant.format));
+        accepted.min_by_key(|variant| {
+            let rank = u32::from(format_rank(&variant.format));
+            if variant.width < width {
+                // Only picked if none is wide enough, so the widest first
+                (true, u32::MAX - variant.width, rank)
+            } else {
+                (false, rank, variant.width)
+            }
+        })
+    }
+}
+
+/// `sizes` attribute, e.g. `(max-width: 600px) 100vw, 50vw`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageSizes {
+    /// Media conditions and the width of the image when they match, in order
+    pub conditions: Vec<(String, String)>,
+    /// Width of the image when no condition matches
+    pub default: String,
+}
+
+impl ImageSizes {
+    pub fn new(default: &str) -> Self {
+        Self {
+            conditions: Vec::new(),
+            default: default.to_string(),
+        }
+    }
+
+    pub fn when(mut self, media_condition: &str, size: &str) -> Self {
+        self.conditions
+            .push((media_condition.to_string(), size.to_string()));
+        self
+    }
+}
+
+impl fmt::Display for ImageSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (condition, size) in &self.conditions {
+            write!(f, "{} {}, ", condition, size)?;
+        }
+        write!(f, "{}", self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::{Decode, Encode};
+
+    fn variant(format: MimeImage, width: u32, density: Option<u16>) -> ImageVariant {
+        ImageVariant {
+            src: MediaSrc::URL(format!(
+                "https://anthol.net/a-{}.{}",
+                width,
+                format.extension().unwrap()
+            )),
+            format,
+            width,
+            height: width / 2,
+            density,
+        }
+    }
+
+    fn image(variants: Vec<ImageVariant>) -> MediaData {
+        variants
+            .into_iter()
+            .fold(
+                MediaData::builder()
+                    .url("https://anthol.net/a.jpg")
+                    .mime(Mime::Image(MimeImage::Jpeg))
+                    .alt("Red mug"),
+                |builder, variant| builder.variant(variant),
+            )
+            .try_build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_srcset() {
+        let config = ResolverConfig::default();
+        let data = image(vec![
+            variant(MimeImage::Jpeg, 1280, None),
+            variant(MimeImage::Webp, 640, None),
+            variant(MimeImage::Jpeg, 640, None),
+        ]);
+        assert_eq!(
+            data.srcset(&MimeImage::Jpeg, &config),
+            Ok(
+                "https://anthol.net/a-640.jpg 640w, https://anthol.net/a-1280.jpg 1280w"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            data.picture_sources(&config).unwrap(),
+            vec![
+                PictureSource {
+                    mime: "image/webp".to_string(),
+                    srcset: "https://anthol.net/a-640.webp 640w".to_string()
+                },
+                PictureSource {
+                    mime: "image/jpeg".to_string(),
+                    srcset:
+                        "https://anthol.net/a-640.jpg 640w, https://anthol.net/a-1280.jpg 1280w"
+                            .to_string()
+                },
+            ]
+        );
+
+        let icon = image(vec![
+            variant(MimeImage::Png, 96, Some(200)),
+            variant(MimeImage::Png, 48, Some(100)),
+            variant(MimeImage::Png, 72, Some(150)),
+        ]);
+        assert_eq!(
+            icon.srcset(&MimeImage::Png, &config),
+            Ok("https://anthol.net/a-48.png 1x, https://anthol.net/a-72.png 1.5x, https://anthol.net/a-96.png 2x".to_string())
+        );
+
+        let duplicates = image(vec![
+            variant(MimeImage::Png, 48, Some(100)),
+            variant(MimeImage::Png, 64, Some(100)),
+            variant(MimeImage::Png, 96, Some(200)),
+        ]);
+        assert_eq!(
+            duplicates.srcset(&MimeImage::Png, &config),
+            Ok("https://anthol.net/a-48.png 1x, https://anthol.net/a-96.png 2x".to_string())
+        );
+        let mixed = image(vec![
+            variant(MimeImage::Jpeg, 640, Some(200)),
+            variant(MimeImage::Jpeg, 1280, Some(100)),
+            variant(MimeImage::Jpeg, 960, None),
+        ]);
+        assert_eq!(
+            mixed.srcset(&MimeImage::Jpeg, &config),
+            Ok("https://anthol.net/a-640.jpg 640w, https://anthol.net/a-960.jpg 960w, https://anthol.net/a-1280.jpg 1280w".to_string())
+        );
+
+        let duplicates = image(vec![
+            variant(MimeImage::Jpeg, 640, None),
+            variant(MimeImage::Jpeg, 640, Some(100)),
+        ]);
+        assert_eq!(
+            duplicates.srcset(&MimeImage::Jpeg, &config),
+            Ok("https://anthol.net/a-640.jpg 640w".to_string())
+        );
+
+        let mut unsafe_variant = variant(MimeImage::Png, 48, None);
+        unsafe_variant.src = MediaSrc::URL("javascript:alert(1)".to_string());
+        assert_eq!(
+            image(vec![unsafe_variant]).srcset(&MimeImage::Png, &config),
+            Err(MediaSrcError::UnsafeScheme("javascript".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_decode_without_variants() {
+        /// Shape of [`MediaData`] before codecs and variants
+        #[derive(CandidType)]
+        struct LegacyMediaData {
+            src: MediaSrc,
+            mime: Mime,
+            alt: Option<String>,
+            decorative: bool,
+            metadata: crate::media::MediaMetadata,
+        }
+
+        let bytes = Encode!(&LegacyMediaData {
+            src: MediaSrc::URL("https://anthol.net/a.jpg".to_string()),
+            mime: Mime::Image(MimeImage::Jpeg),
+            alt: Some("Red mug".to_string()),
+            decorative: false,
+            metadata: Default::default(),
+        })
+        .unwrap();
+        let data = Decode!(&bytes, MediaData).unwrap();
+        assert_eq!(data.variants, None);
+        assert!(data.image_variants().is_empty());
+        assert_eq!(
+            data.srcset(&MimeImage::Jpeg, &ResolverConfig::default()),
+            Ok(String::new())
+        );
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(ImageSizes::new("100vw").to_string(), "100vw");
+        assert_eq!(
+            ImageSizes::new("33vw")
+                .when("(max-width: 600px)", "100vw")
+                .when("(max-width: 1200px)", "50vw")
+                .to_string(),
+            "(max-width: 600px) 100vw, (max-width: 1200px) 50vw, 33vw"
+        );
+    }
+
+    #[test]
+    fn test_pick_variant() {
+        let data = image(vec![
+            variant(MimeImage::Jpeg, 640, None),
+            variant(MimeImage::Jpeg, 1280, None),
+            variant(MimeImage::Webp, 640, None),
+            variant(MimeImage::Webp, 1280, None),
+            variant(MimeImage::Avif, 1280, None),
+        ]);
+        let all = [MimeImage::Avif, MimeImage::Webp, MimeImage::Jpeg];
+        let pick = |width, accepted: &[MimeImage]| {
+            data.pick_variant(width, accepted)
+                .map(|variant| (variant.format.clone(), variant.width))
+        };
+
+        assert_eq!(pick(600, &all), Some((MimeImage::Avif, 1280)));
+        assert_eq!(pick(600, &[MimeImage::Webp]), Some((MimeImage::Webp, 640)));
+        assert_eq!(pick(1000, &all), Some((MimeImage::Avif, 1280)));
+        assert_eq!(pick(2000, &all), Some((MimeImage::Avif, 1280)));
+        assert_eq!(
+            pick(1000, &[MimeImage::Jpeg]),
+            Some((MimeImage::Jpeg, 1280))
+        );
+        assert_eq!(pick(600, &[MimeImage::Png]), None);
+
+        let data = image(vec![
+            variant(MimeImage::Jpeg, 640, None),
+            variant(MimeImage::Avif, 800, None),
+        ]);
+        let pick = |width, accepted: &[MimeImage]| {
+            data.pick_variant(width, accepted)
+                .map(|variant| (variant.format.clone(), variant.width))
+        };
+        assert_eq!(pick(600, &all), Some((MimeImage::Avif, 800)));
+        assert_eq!(pick(700, &all), Some((MimeImage::Avif, 800)));
+        assert_eq!(pick(900, &all), Some((MimeImage::Avif, 800)));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{market::MarketDataResponseWithItemGlances, unit::Currency};
+use crate::{market::MarketDataResponseWithItemGlances, unit::Currency, util::locale::LanguageTag};
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +11,8 @@ pub enum HomePageError {
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
 pub struct HomePageRequest {
     pub currency: Currency,
+    /// Languages of the shopper, most preferred first, absent in requests from older clients
+    pub languages: Option<Vec<LanguageTag>>,
 }
 
 #[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
@@ -1,4 +1,10 @@
-use crate::id::{Id, IdError};
+use crate::{
+    typed_id,
+    util::{
+        locale::LocalizedText,
+        name::{normalize_display_name, normalize_name, NameError},
+    },
+};
 use candid::{CandidType, Principal};
 use derive_more::{AsRef, Display, From, Into};
 use ic_stable_structures::{storable::Bound, Storable};
@@ -24,54 +30,10 @@ use std::str::FromStr;
 )]
 pub struct StorePrincipal(Principal);
 
-#[derive(
-    CandidType,
-    Clone,
-    Copy,
-    Serialize,
-    Deserialize,
-    Debug,
-    Hash,
-    Eq,
-    PartialEq,
-    PartialOrd,
-    Ord,
-    Default,
-    AsRef,
-    From,
-    Display,
-)]
-pub struct StoreId(Id);
-
-impl StoreId {
-    pub fn new<T: AsRef<str>>(id: T) -> Result<Self, IdError> {
-        Ok(StoreId(Id::new(id)?))
-    }
-}
-
-impl FromStr for StoreId {
-    type Err = IdError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(StoreId(Id::from_str(s)?))
-    }
-}
-
-impl TryFrom<&str> for StoreId {
-    type Error = IdError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(StoreId(Id::from_str(value)?))
-    }
-}
-
-impl TryFrom<String> for StoreId {
-    type Error = IdError;
-
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        Ok(StoreId(Id::from_str(&value)?))
-    }
-}
+typed_id!(
+    /// Wrapper for the store id.
+    pub StoreId, StoreIdKind, "sto"
+);
 
 impl Storable for StorePrincipal {
     fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
@@ -88,22 +50,9 @@ impl Storable for StorePrincipal {
     };
 }
 
-impl Storable for StoreId {
-    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
-        self.0.to_bytes()
-    }
-
-    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
-        Self(Id::from_bytes(bytes))
-    }
-
-    const BOUND: Bound = Bound::Bounded {
-        max_size: 16,
-        is_fixed_size: false,
-    };
-}
-
-/// Store name up to 50 characters
+/// Store name up to 50 characters.
+///
+/// Names are validated by [`normalize_display_name`].
 #[derive(
     CandidType,
     Clone,
@@ -123,15 +72,24 @@ impl Storable for StoreId {
 pub struct StoreName(String);
 
 impl StoreName {
+    /// The maximum length of a name in grapheme clusters
+    pub const MAX_LENGTH: usize = 50;
+    /// The maximum length of a name in bytes
+    pub const MAX_LENGTH_IN_BYTES: usize = 200;
+
     pub fn new<T: AsRef<str>>(name: T) -> Result<Self, StoreNameError> {
         Self::from_str_core(name.as_ref())
     }
 
+    /// Create a name for one of the platform's own stores, which may use a reserved name
+    pub fn new_for_platform<T: AsRef<str>>(name: T) -> Result<Self, StoreNameError> {
+        let name = normalize_name(name.as_ref(), Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(StoreName(name))
+    }
+
     fn from_str_core(s: &str) -> Result<Self, StoreNameError> {
-        if s.chars().count() > 50 {
-            return Err(StoreNameError::TooLong);
-        }
-        Ok(StoreName(s.to_string()))
+        let name = normalize_display_name(s, Self::MAX_LENGTH, Self::MAX_LENGTH_IN_BYTES)?;
+        Ok(StoreName(name))
     }
 }
 
@@ -169,21 +127,17 @@ impl Storable for StoreName {
     }
 
     const BOUND: Bound = Bound::Bounded {
-        max_size: 200,
+        max_size: Self::MAX_LENGTH_IN_BYTES as u32,
         is_fixed_size: false,
     };
 }
 
-#[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize, CandidType)]
-pub enum StoreNameError {
-    #[error("Provided store name is too long")]
-    TooLong,
-}
+pub type StoreNameError = NameError;
 
 #[derive(Debug, Clone, Serialize, Deserialize, CandidType)]
 pub struct StoreInitArg {
     pub id: StoreId,
-    pub name: StoreName,
+    pub name: LocalizedText<StoreName>,
 }
 
 #[cfg(test)]
@@ -210,6 +164,19 @@ mod tests {
         let name = StoreName::new("𐍈".repeat(50)).unwrap(); // '𐍈' is 4 bytes character
         assert_eq!(name.to_string(), "𐍈".repeat(50));
         assert_eq!(name.to_bytes().len(), 200);
+
+        let name = StoreName::new("  Anthol\u{3000}Store ").unwrap();
+        assert_eq!(name.to_string(), "Anthol Store");
+        assert_eq!(StoreName::new(""), Err(StoreNameError::Empty));
+        assert_eq!(
+            StoreName::new("Anthol\u{202E}Store"),
+            Err(StoreNameError::InvalidCharacter("\u{202E}".to_string()))
+        );
+        assert_eq!(StoreName::new("Anthol"), Err(StoreNameError::Reserved));
+        assert_eq!(
+            StoreName::new_for_platform("Anthol").unwrap().to_string(),
+            "Anthol"
+        );
     }
 
     #[test]
@@ -1,3 +1,7 @@
+pub mod locale;
+pub mod mail;
+pub mod name;
 pub mod network;
+pub mod rng;
 pub mod scale;
 pub mod string;
@@ -0,0 +1,366 @@
+use candid::{CandidType, Decode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// BCP 47 language tag such as `en`, `ja-JP` or `zh-Hant-TW`.
+///
+/// Tags are stored in their canonical case: lowercase language, titlecase script and
+/// uppercase region, so that they can be compared and used as map keys directly.
+#[derive(
+    CandidType, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, PartialOrd, Ord,
+)]
+#[serde(try_from = "String")]
+pub struct LanguageTag(String);
+
+impl LanguageTag {
+    /// The maximum length of a tag in bytes, as recommended by RFC 5646
+    pub const MAX_LENGTH: usize = 35;
+
+    pub fn new<T: AsRef<str>>(tag: T) -> Result<Self, LanguageTagError> {
+        Self::from_str_core(tag.as_ref())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the tags to try for this tag in lookup order, e.g. `zh-Hant-TW`, `zh-Hant`, `zh`.
+    ///
+    /// Singleton subtags such as `x` are never left at the end of a fallback.
+    pub fn fallbacks(&self) -> impl Iterator<Item = &str> {
+        let mut next = Some(self.0.as_str());
+        std::iter::from_fn(move || {
+            let current = next?;
+            let mut rest = current;
+            next = loop {
+                match rest.rfind('-') {
+                    Some(index) => {
+                        rest = &rest[..index];
+                        if rest.len() - rest.rfind('-').map_or(0, |i| i + 1) > 1 {
+                            break Some(rest);
+                        }
+                    }
+                    None => break None,
+                }
+            };
+            Some(current)
+        })
+    }
+
+    fn from_str_core(s: &str) -> Result<Self, LanguageTagError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(LanguageTagError::Empty);
+        }
+        if s.len() > Self::MAX_LENGTH {
+            return Err(LanguageTagError::TooLong);
+        }
+
+        let mut tag = String::with_capacity(s.len());
+        let mut after_singleton = false;
+        for (position, subtag) in s.split(['-', '_']).enumerate() {
+            let is_valid = (1..=8).contains(&subtag.len())
+                && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+                && (position > 0
+                    || subtag.eq_ignore_ascii_case("x")
+                    || (subtag.chars().all(|c| c.is_ascii_alphabetic())
+                        && matches!(subtag.len(), 2 | 3 | 5..=8)));
+            if !is_valid {
+                return Err(LanguageTagError::InvalidSubtag(subtag.to_string()));
+            }
+
+            if position > 0 {
+                tag.push('-');
+            }
+            let is_alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+            match subtag.len() {
+                4 if position > 0 && !after_singleton && is_alphabetic => {
+                    tag.push_str(&subtag[..1].to_ascii_uppercase());
+                    tag.push_str(&subtag[1..].to_ascii_lowercase());
+                }
+                2 if position > 0 && !after_singleton && is_alphabetic => {
+                    tag.push_str(&subtag.to_ascii_uppercase())
+                }
+                _ => tag.push_str(&subtag.to_ascii_lowercase()),
+            }
+            after_singleton |= subtag.len() == 1;
+        }
+        // Singletons introduce extensions and cannot end a tag
+        if let Some(last) = s.rsplit(['-', '_']).next().filter(|last| last.len() == 1) {
+            return Err(LanguageTagError::InvalidSubtag(last.to_string()));
+        }
+
+        Ok(Self(tag))
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for LanguageTag {
+    type Err = LanguageTagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_core(s)
+    }
+}
+
+impl TryFrom<&str> for LanguageTag {
+    type Error = LanguageTagError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str_core(value)
+    }
+}
+
+impl TryFrom<String> for LanguageTag {
+    type Error = LanguageTagError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str_core(&value)
+    }
+}
+
+/// Parse an `Accept-Language` header into language tags, most preferred first.
+///
+/// Wildcards, invalid tags and tags with `q=0` are skipped.
+pub fn parse_accept_language(header: &str) -> Vec<LanguageTag> {
+    let mut tags = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = LanguageTag::new(parts.next()?).ok()?;
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then_some((tag, quality))
+        })
+        .collect::<Vec<_>>();
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Text with translations keyed by language tag.
+///
+/// `default` is shown when none of the preferred languages is available.
+/// Plain values stored before localization are migrated with [`LocalizedText::from`] or
+/// [`LocalizedText::decode_or_migrate`].
+#[derive(CandidType, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Default)]
+pub struct LocalizedText<T = String> {
+    pub default: T,
+    pub translations: BTreeMap<LanguageTag, T>,
+}
+
+impl<T> LocalizedText<T> {
+    pub fn new(default: T) -> Self {
+        Self {
+            default,
+            translations: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_translation(mut self, language: LanguageTag, text: T) -> Self {
+        self.translations.insert(language, text);
+        self
+    }
+
+    /// Find the translation for the most preferred language, following the lookup scheme of
+    /// RFC 4647, i.e. `zh-Hant-TW` falls back to `zh-Hant` and then to `zh`
+    pub fn lookup(&self, preferred: &[LanguageTag]) -> Option<(&LanguageTag, &T)> {
+        preferred
+            .iter()
+            .flat_map(LanguageTag::fallbacks)
+            .find_map(|fallback| {
+                self.translations
+                    .iter()
+                    .find(|(language, _)| language.as_str() == fallback)
+            })
+    }
+
+    /// Get the text for the most preferred language, or the default one
+    pub fn get(&self, preferred: &[LanguageTag]) -> &T {
+        self.lookup(preferred)
+            .map_or(&self.default, |(_, text)| text)
+    }
+
+    /// Keep only the translation for the most preferred language, for responses
+    pub fn localize(&self, preferred: &[LanguageTag]) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            default: self.default.clone(),
+            translations: self
+                .lookup(preferred)
+                .map(|(language, text)| (language.clone(), text.clone()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Convert the default and every translation, e.g. to validate names
+    pub fn try_map<U, E>(
+        self,
+        mut f: impl FnMut(T) -> Result<U, E>,
+    ) -> Result<LocalizedText<U>, E> {
+        Ok(LocalizedText {
+            default: f(self.default)?,
+            translations: self
+                .translations
+                .into_iter()
+                .map(|(language, text)| Ok((language, f(text)?)))
+                .collect::<Result<_, E>>()?,
+        })
+    }
+}
+
+impl<T> From<T> for LocalizedText<T> {
+    fn from(default: T) -> Self {
+        Self::new(default)
+    }
+}
+
+impl<T: CandidType + DeserializeOwned> LocalizedText<T> {
+    /// Decode Candid bytes of a localized text, migrating bytes of a plain value stored before
+    /// localization to a text without translations
+    pub fn decode_or_migrate(bytes: &[u8]) -> Result<Self, candid::Error> {
+        Decode!(bytes, Self).or_else(|_| Decode!(bytes, T).map(Self::new))
+    }
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LanguageTagError {
+    #[error("Language tag is empty")]
+    Empty,
+    #[error("Language tag is too long")]
+    TooLong,
+    #[error("Language tag contains an invalid subtag ({0:?})")]
+    InvalidSubtag(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Encode;
+
+    fn tags(tags: &[&str]) -> Vec<LanguageTag> {
+        tags.iter()
+            .map(|tag| LanguageTag::new(tag).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_language_tag() {
+        assert_eq!(LanguageTag::new("en").unwrap().as_str(), "en");
+        assert_eq!(LanguageTag::new("JA_jp").unwrap().as_str(), "ja-JP");
+        assert_eq!(
+            LanguageTag::new("zh-hant-tw").unwrap().as_str(),
+            "zh-Hant-TW"
+        );
+        assert_eq!(
+            LanguageTag::new("en-x-Abcd-us").unwrap().as_str(),
+            "en-x-abcd-us"
+        );
+
+        assert_eq!(LanguageTag::new(""), Err(LanguageTagError::Empty));
+        assert_eq!(
+            LanguageTag::new("e"),
+            Err(LanguageTagError::InvalidSubtag("e".to_string()))
+        );
+        assert_eq!(
+            LanguageTag::new("en-x"),
+            Err(LanguageTagError::InvalidSubtag("x".to_string()))
+        );
+        assert_eq!(
+            LanguageTag::new("en--US"),
+            Err(LanguageTagError::InvalidSubtag("".to_string()))
+        );
+        assert_eq!(
+            LanguageTag::new("en-toolongsubtag"),
+            Err(LanguageTagError::InvalidSubtag("toolongsubtag".to_string()))
+        );
+        assert!(serde_json::from_str::<LanguageTag>("\"en US\"").is_err());
+    }
+
+    #[test]
+    fn test_fallbacks() {
+        // Example from RFC 4647
+        let tag = LanguageTag::new("zh-Hant-CN-x-private1-private2").unwrap();
+        assert_eq!(
+            tag.fallbacks().collect::<Vec<_>>(),
+            [
+                "zh-Hant-CN-x-private1-private2",
+                "zh-Hant-CN-x-private1",
+                "zh-Hant-CN",
+                "zh-Hant",
+                "zh"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(
+            parse_accept_language("en;q=0.5, ja-JP, *;q=0.1, fr;q=0, ja;q=0.8, x"),
+            tags(&["ja-JP", "ja", "en"])
+        );
+    }
+
+    #[test]
+    fn test_localized_text() {
+        let text = LocalizedText::new("Apple".to_string())
+            .with_translation(LanguageTag::new("ja").unwrap(), "りんご".to_string())
+            .with_translation(LanguageTag::new("zh-Hant").unwrap(), "蘋果".to_string());
+
+        assert_eq!(text.get(&tags(&["ja-JP", "en"])), "りんご");
+        assert_eq!(text.get(&tags(&["zh-Hant-TW"])), "蘋果");
+        assert_eq!(text.get(&tags(&["zh"])), "Apple");
+        assert_eq!(text.get(&tags(&["fr", "ja"])), "りんご");
+        assert_eq!(text.get(&[]), "Apple");
+
+        let localized = text.localize(&tags(&["ja"]));
+        assert_eq!(localized.translations.len(), 1);
+        assert_eq!(localized.get(&tags(&["ja"])), "りんご");
+        assert_eq!(
+            text.localize(&tags(&["fr"])),
+            LocalizedText::from("Apple".to_string())
+        );
+    }
+
+    #[test]
+    fn test_localized_text_serde() {
+        let text = LocalizedText::new("Apple".to_string())
+            .with_translation(LanguageTag::new("ja").unwrap(), "りんご".to_string());
+        let json = serde_json::to_string(&text).unwrap();
+        assert_eq!(
+            json,
+            r#"{"default":"Apple","translations":{"ja":"りんご"}}"#
+        );
+        assert_eq!(serde_json::from_str::<LocalizedText>(&json).unwrap(), text);
+
+        let bin = bincode::serialize(&text).unwrap();
+        assert_eq!(bincode::deserialize::<LocalizedText>(&bin).unwrap(), text);
+
+        let bytes = Encode!(&text).unwrap();
+        assert_eq!(Decode!(&bytes, LocalizedText).unwrap(), text);
+    }
+
+    #[test]
+    fn test_localized_text_migration() {
+        let text = LocalizedText::new("Apple".to_string())
+            .with_translation(LanguageTag::new("ja").unwrap(), "りんご".to_string());
+        let bytes = Encode!(&text).unwrap();
+        assert_eq!(LocalizedText::decode_or_migrate(&bytes).unwrap(), text);
+
+        let bytes = Encode!(&"Apple".to_string()).unwrap();
+        assert_eq!(
+            LocalizedText::<String>::decode_or_migrate(&bytes).unwrap(),
+            LocalizedText::from("Apple".to_string())
+        );
+        assert!(LocalizedText::<String>::decode_or_migrate(&Encode!(&1u8).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// The maximum length of a mail address in bytes, limited by the SMTP path length
+pub const MAX_MAIL_ADDRESS_LENGTH: usize = 254;
+/// The maximum length of the local part in bytes
+pub const MAX_LOCAL_PART_LENGTH: usize = 64;
+
+/// Validate the syntax of a mail address such as `shopper@example.com`.
+///
+/// Only the dot-atom form of RFC 5322 is accepted: quoted local parts, comments and IP literal
+/// domains are rejected, as are non-ASCII addresses. The domain needs at least two labels.
+pub fn validate_mail_address(address: &str) -> Result<(), MailAddressError> {
+    if address.is_empty() {
+        return Err(MailAddressError::Empty);
+    }
+    if address.len() > MAX_MAIL_ADDRESS_LENGTH {
+        return Err(MailAddressError::TooLong);
+    }
+
+    let (local, domain) = address
+        .rsplit_once('@')
+        .ok_or(MailAddressError::MissingAt)?;
+
+    if local.is_empty()
+        || local.len() > MAX_LOCAL_PART_LENGTH
+        || local.split('.').any(|atom| {
+            atom.is_empty()
+                || !atom
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-".contains(c))
+        })
+    {
+        return Err(MailAddressError::InvalidLocalPart);
+    }
+
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let is_valid_label = |label: &&str| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if labels.len() < 2
+        || !labels.iter().all(is_valid_label)
+        || labels.last().unwrap().chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(MailAddressError::InvalidDomain);
+    }
+
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MailAddressError {
+    #[error("Mail address is empty")]
+    Empty,
+    #[error("Mail address is too long")]
+    TooLong,
+    #[error("Mail address has no '@'")]
+    MissingAt,
+    #[error("Mail address has an invalid part before '@'")]
+    InvalidLocalPart,
+    #[error("Mail address has an invalid domain")]
+    InvalidDomain,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_mail_address() {
+        assert!(validate_mail_address("shopper@example.com").is_ok());
+        assert!(validate_mail_address("first.last+tag@mail.example.co.jp").is_ok());
+        assert!(validate_mail_address("o'neil_{x}@example-shop.io").is_ok());
+
+        assert_eq!(validate_mail_address(""), Err(MailAddressError::Empty));
+        assert_eq!(
+            validate_mail_address("shopper.example.com"),
+            Err(MailAddressError::MissingAt)
+        );
+        for address in [
+            "@example.com",
+            ".shopper@example.com",
+            "shop..per@example.com",
+            "shop per@example.com",
+            "\"shopper\"@example.com",
+            "shöpper@example.com",
+        ] {
+            assert_eq!(
+                validate_mail_address(address),
+                Err(MailAddressError::InvalidLocalPart),
+                "{}",
+                address
+            );
+        }
+        for address in [
+            "shopper@",
+            "shopper@localhost",
+            "shopper@example..com",
+            "shopper@-example.com",
+            "shopper@example.com.",
+            "shopper@192.168.0.1",
+            "shopper@exa_mple.com",
+        ] {
+            assert_eq!(
+                validate_mail_address(address),
+                Err(MailAddressError::InvalidDomain),
+                "{}",
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn test_mail_address_length() {
+        let local = "a".repeat(MAX_LOCAL_PART_LENGTH);
+        assert!(validate_mail_address(&format!("{}@example.com", local)).is_ok());
+        assert_eq!(
+            validate_mail_address(&format!("a{}@example.com", local)),
+            Err(MailAddressError::InvalidLocalPart)
+        );
+
+        let domain = format!("{}.com", vec!["a".repeat(63); 4].join("."));
+        assert_eq!(
+            validate_mail_address(&format!("shopper@{}", domain)),
+            Err(MailAddressError::TooLong)
+        );
+    }
+}
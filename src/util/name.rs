@@ -0,0 +1,181 @@
+use crate::util::string::adjust_whitespaces;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Names that could be mistaken for the platform or its staff, compared case-insensitively
+const RESERVED_NAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "anthol",
+    "moderator",
+    "official",
+    "root",
+    "staff",
+    "support",
+    "system",
+];
+
+/// Normalize and validate a display name such as an actor or store name.
+///
+/// The name is validated by [`normalize_name`] and must not be one of the names reserved for
+/// the platform and its staff.
+///
+/// # Errors
+///
+/// Returns an error if [`normalize_name`] fails or the normalized name is reserved.
+pub fn normalize_display_name(
+    name: &str,
+    max_length: usize,
+    max_length_in_bytes: usize,
+) -> Result<String, NameError> {
+    let name = normalize_name(name, max_length, max_length_in_bytes)?;
+    if RESERVED_NAMES.contains(&name.to_lowercase().as_str()) {
+        return Err(NameError::Reserved);
+    }
+    Ok(name)
+}
+
+/// Normalize and validate a name without checking the reserved names.
+///
+/// Use this for names that can't impersonate the platform, e.g. birth names, and for the
+/// platform's own actors, stores and markets, which may use reserved names such as `Anthol`.
+///
+/// The name is normalized to NFC, trimmed and its whitespaces are collapsed into single spaces.
+/// The length is counted in grapheme clusters, so that `é` and `👍🏽` count as one character,
+/// and the byte length is limited as well to keep stacked combining marks in check.
+///
+/// # Errors
+///
+/// Returns an error if the normalized name is empty, too long, or contains control, format,
+/// private-use or non-characters, e.g. zero-width joiners and bidi overrides.
+pub fn normalize_name(
+    name: &str,
+    max_length: usize,
+    max_length_in_bytes: usize,
+) -> Result<String, NameError> {
+    let name = adjust_whitespaces(&name.nfc().collect::<String>());
+
+    if let Some(c) = name.chars().find(|&c| is_disallowed(c)) {
+        return Err(NameError::InvalidCharacter(c.to_string()));
+    }
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.graphemes(true).count() > max_length || name.len() > max_length_in_bytes {
+        return Err(NameError::TooLong);
+    }
+
+    Ok(name)
+}
+
+/// Characters that are invisible or change the rendering of surrounding text
+fn is_disallowed(c: char) -> bool {
+    c.is_control()
+        || (c as u32) & 0xFFFE == 0xFFFE // non-characters at the end of every plane
+        || matches!(
+            c,
+            '\u{00AD}' // soft hyphen
+                | '\u{034F}' // combining grapheme joiner
+                | '\u{061C}' // arabic letter mark
+                | '\u{115F}' | '\u{1160}' | '\u{3164}' | '\u{FFA0}' // hangul fillers
+                | '\u{180E}' // mongolian vowel separator
+                | '\u{200B}'..='\u{200F}' // zero-width spaces, joiners and direction marks
+                | '\u{202A}'..='\u{202E}' // bidi embeddings and overrides
+                | '\u{2060}'..='\u{206F}' // word joiner, invisible operators and bidi isolates
+                | '\u{FDD0}'..='\u{FDEF}' // non-characters
+                | '\u{FEFF}' // byte order mark
+                | '\u{FFF9}'..='\u{FFFB}' // interlinear annotations
+                | '\u{E000}'..='\u{F8FF}' // private use
+                | '\u{E0000}'..='\u{E007F}' // tags
+                | '\u{F0000}'..='\u{10FFFF}' // supplementary private use
+        )
+}
+
+/// Errors that can occur when validating actor, store and market names
+#[derive(thiserror::Error, Debug, CandidType, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NameError {
+    #[error("Name is too long")]
+    TooLong,
+    #[error("Name is empty")]
+    Empty,
+    /// The offending character, as a string since Candid has no character type
+    #[error("Name contains an invalid character ({0:?})")]
+    InvalidCharacter(String),
+    #[error("Name is reserved")]
+    Reserved,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_display_name() {
+        assert_eq!(
+            normalize_display_name("  Anthol \t\n Market ", 50, 200).unwrap(),
+            "Anthol Market"
+        );
+        // Decomposed "é" is composed into a single code point
+        assert_eq!(
+            normalize_display_name("Cafe\u{0301}", 50, 200).unwrap(),
+            "Caf\u{00E9}"
+        );
+        assert_eq!(
+            normalize_display_name("\u{00A0}アンソル\u{3000}", 50, 200).unwrap(),
+            "アンソル"
+        );
+    }
+
+    #[test]
+    fn test_display_name_length() {
+        assert!(normalize_display_name(&"a".repeat(50), 50, 200).is_ok());
+        assert_eq!(
+            normalize_display_name(&"a".repeat(51), 50, 200),
+            Err(NameError::TooLong)
+        );
+        // Each flag is one grapheme of two code points
+        assert!(normalize_display_name(&"🇯🇵".repeat(25), 25, 200).is_ok());
+        // Stacked combining marks are limited by bytes
+        assert_eq!(
+            normalize_display_name(&format!("a{}", "\u{0301}".repeat(150)), 50, 200),
+            Err(NameError::TooLong)
+        );
+    }
+
+    #[test]
+    fn test_display_name_invalid() {
+        assert_eq!(
+            normalize_display_name(" \t ", 50, 200),
+            Err(NameError::Empty)
+        );
+        assert_eq!(
+            normalize_display_name("ab\u{200D}c", 50, 200),
+            Err(NameError::InvalidCharacter("\u{200D}".to_string()))
+        );
+        assert_eq!(
+            normalize_display_name("abc\u{202E}cba", 50, 200),
+            Err(NameError::InvalidCharacter("\u{202E}".to_string()))
+        );
+        assert_eq!(
+            normalize_display_name("abc\u{0007}", 50, 200),
+            Err(NameError::InvalidCharacter("\u{0007}".to_string()))
+        );
+        assert_eq!(
+            normalize_display_name("\u{E000}", 50, 200),
+            Err(NameError::InvalidCharacter("\u{E000}".to_string()))
+        );
+        assert_eq!(
+            normalize_display_name(" Anthol ", 50, 200),
+            Err(NameError::Reserved)
+        );
+        assert!(normalize_display_name("Anthol Market", 50, 200).is_ok());
+    }
+
+    #[test]
+    fn test_name_allows_reserved() {
+        assert_eq!(normalize_name(" Anthol ", 50, 200).unwrap(), "Anthol");
+        assert_eq!(normalize_name(" \t ", 50, 200), Err(NameError::Empty));
+    }
+}
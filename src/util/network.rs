@@ -1,6 +1,7 @@
 use dotenvy_macro::dotenv;
 use std::env;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DfxNetwork {
     Local,
     Ic,
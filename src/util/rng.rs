@@ -0,0 +1,168 @@
+use ic_cdk::api::call::RejectionCode;
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+use std::cell::RefCell;
+
+/// Number of bytes drawn from one seed before the pool has to be refilled from `raw_rand`.
+const BYTES_PER_SEED: u64 = 1 << 20;
+
+/// Seed used instead of `raw_rand` in tests and with the `off-chain-seed` feature, so that
+/// off-chain runs are reproducible. Never enable that feature for a deployed canister.
+#[cfg(any(test, feature = "off-chain-seed"))]
+pub const OFF_CHAIN_SEED: [u8; 32] = *b"anthol-off-chain-entropy-seed-00";
+
+thread_local! {
+    static POOL: RefCell<EntropyPool> = const { RefCell::new(EntropyPool::new()) };
+}
+
+/// Cryptographically secure random number generator seeded from the management canister.
+///
+/// One `raw_rand` call seeds a ChaCha20 stream that can hand out up to 1 MiB of random bytes
+/// synchronously. Before the first seed, or once too few bytes are left for a draw, drawing fails
+/// with [`RngError::NotSeeded`] until the pool is refilled by [`ensure_seeded`].
+pub struct EntropyPool {
+    rng: Option<ChaCha20Rng>,
+    remaining_bytes: u64,
+}
+
+impl EntropyPool {
+    /// Create an unseeded pool
+    pub const fn new() -> Self {
+        Self {
+            rng: None,
+            remaining_bytes: 0,
+        }
+    }
+
+    /// Create a pool seeded with the given seed, e.g. a fixed one for deterministic tests
+    pub fn with_seed(seed: [u8; 32]) -> Self {
+        let mut pool = Self::new();
+        pool.seed(seed);
+        pool
+    }
+
+    /// Replace the current stream with one derived from the seed
+    pub fn seed(&mut self, seed: [u8; 32]) {
+        self.rng = Some(ChaCha20Rng::from_seed(seed));
+        self.remaining_bytes = BYTES_PER_SEED;
+    }
+
+    /// Whether the pool has to be refilled before drawing `min_bytes` bytes from it
+    pub fn needs_seed(&self, min_bytes: usize) -> bool {
+        self.rng.is_none() || self.remaining_bytes < (min_bytes as u64).max(1)
+    }
+
+    /// Fill the buffer with random bytes
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RngError> {
+        let len = dest.len() as u64;
+        match self.rng.as_mut() {
+            Some(rng) if len <= self.remaining_bytes => {
+                rng.fill_bytes(dest);
+                self.remaining_bytes -= len;
+                Ok(())
+            }
+            _ => Err(RngError::NotSeeded),
+        }
+    }
+
+    /// Draw a random `u64`
+    pub fn next_u64(&mut self) -> Result<u64, RngError> {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+impl Default for EntropyPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run a closure with the canister-wide entropy pool
+pub fn with_pool<R>(f: impl FnOnce(&mut EntropyPool) -> R) -> R {
+    POOL.with(|pool| f(&mut pool.borrow_mut()))
+}
+
+/// Seed the canister-wide entropy pool from `raw_rand` if it is unseeded or has fewer than
+/// `min_bytes` bytes left.
+///
+/// Call this at the start of an update call with the number of random bytes it draws; the values
+/// themselves can then be drawn synchronously.
+/// In tests and with the `off-chain-seed` feature the pool is seeded with [`OFF_CHAIN_SEED`]
+/// instead.
+///
+/// # Errors
+///
+/// Returns [`RngError::SeedRequest`] if the `raw_rand` call fails.
+pub async fn ensure_seeded(min_bytes: usize) -> Result<(), RngError> {
+    if with_pool(|pool| pool.needs_seed(min_bytes)) {
+        let seed = fetch_seed().await?;
+        with_pool(|pool| pool.seed(seed));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(test, feature = "off-chain-seed")))]
+async fn fetch_seed() -> Result<[u8; 32], RngError> {
+    use ic_cdk::api::management_canister::main::raw_rand;
+
+    let (random_bytes,) = raw_rand()
+        .await
+        .map_err(|(code, message)| RngError::SeedRequest { code, message })?;
+    random_bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| RngError::SeedRequest {
+            code: RejectionCode::CanisterError,
+            message: format!(
+                "raw_rand returned {} bytes instead of 32",
+                random_bytes.len()
+            ),
+        })
+}
+
+#[cfg(any(test, feature = "off-chain-seed"))]
+async fn fetch_seed() -> Result<[u8; 32], RngError> {
+    Ok(OFF_CHAIN_SEED)
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum RngError {
+    #[error("entropy pool is not seeded or its seed is used up (RngError::NotSeeded)")]
+    NotSeeded,
+    #[error("failed to fetch a seed from raw_rand: {message} ({code:?}) (RngError::SeedRequest)")]
+    SeedRequest {
+        code: RejectionCode,
+        message: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reseed_when_too_few_bytes_are_left() {
+        let mut pool = EntropyPool::new();
+        assert!(pool.needs_seed(0));
+        assert_eq!(pool.next_u64(), Err(RngError::NotSeeded));
+
+        pool.seed([1; 32]);
+        let mut bytes = vec![0; BYTES_PER_SEED as usize - 5];
+        pool.fill_bytes(&mut bytes).unwrap();
+
+        // 5 bytes are left, which is enough for a 5-byte draw but not for a `u64`
+        assert!(!pool.needs_seed(5));
+        assert!(pool.needs_seed(8));
+        assert_eq!(pool.next_u64(), Err(RngError::NotSeeded));
+        pool.fill_bytes(&mut [0; 5]).unwrap();
+        assert!(pool.needs_seed(0));
+
+        pool.seed([2; 32]);
+        assert!(!pool.needs_seed(8));
+        assert!(pool.next_u64().is_ok());
+    }
+}